@@ -15,7 +15,7 @@
 
 2. **No manual locking syntax**: Rust's RAII is even more explicit - the lock guard variable (`cache`) must exist to access the data
 
-3. **Poisoning**: Rust locks can be "poisoned" if a thread panics while holding a lock, which is why we use `.unwrap()` (or could handle errors explicitly)
+3. **Poisoning**: Rust locks can be "poisoned" if a thread panics while holding a lock. The cache recovers the guard with `PoisonError::into_inner()` instead of panicking on every later access, reports the event through `is_poisoned()`, and the `_checked` variants (`try_read_checked()`, `write_checked()`) return `CacheError::Poisoned` rather than use the recovered data
 
 4. **Move semantics**: Variables are moved into closures with `move`, and we clone `Arc` pointers with `Arc::clone()`
 
@@ -28,164 +28,4878 @@ The behavior and output will be identical to the C++ version, including the "Not
  * Reader-Writer Pattern Demo using RwLock
  * Compile: rustc reader_writer_pattern.rs
  * Or with Cargo: cargo run
+ * Benchmark only: ./reader_writer_pattern --bench
  * 
  * Demonstrates:
  * - Multiple concurrent readers with read()
  * - Exclusive writer access with write()
  * - Thread-safe cache implementation using Arc and RwLock
+ * - Benchmark of RwLock against Mutex under read-heavy load (--bench)
+ * - Saving the cache to a file and warming a new one from it
+ * - Generic over key and value types
+ * - Recovering from lock poisoning instead of panicking on every access,
+ *   or reporting it as CacheError::Poisoned from the _checked calls
+ * - Bounded cache with LRU or CLOCK (second-chance) eviction
+ * - Hit/miss/write statistics kept in sharded atomic counters
+ * - Pluggable eviction policies: LRU, LFU and CLOCK behind one trait
+ * - Blocking wait and take that park on a Condvar until a key is written
+ * - O(1) LRU cache built on an arena-backed linked list behind a Mutex
+ * - Writer escalation to stop a constant reader stream starving writers
+ * - Lock-free bloom filter in front of a read-through loader
+ * - One Interrupter token waking blocked operations across several caches
+ * - Cancellable worker sleeps with a shared CancellationToken
+ * - O(1) invalidation of every entry by bumping a generation counter
  */
 
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock, Mutex};
+use std::collections::hash_map::DefaultHasher;
+use std::borrow::Borrow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+use std::fs::File;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::iter::FromIterator;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::ops::{Deref, DerefMut};
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::{Arc, Condvar, LockResult, RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::sync::{Mutex, OnceLock, PoisonError, TryLockError, TryLockResult, Weak};
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 /**
- * Thread-safe cache using the Reader-Writer pattern
+ * Reader-writer lock that escalates writers who have waited too long
  * 
- * Uses RwLock to allow:
- * - Multiple simultaneous readers (shared access)
- * - Exclusive writer access (blocks all readers and other writers)
+ * Wraps a std RwLock and puts a gate in front of it. Below the threshold
+ * readers are preferred: a waiting writer only gets in when no reader
+ * holds the lock, which maximises read throughput. Once a writer has
+ * waited `max_wait` it is escalated and the gate closes to new readers;
+ * the readers already inside drain and the writer acquires the lock.
+ * Without a max_wait the gate is never used and this is a plain RwLock.
  * 
- * Wrapped in Arc for shared ownership across threads
+ * Poisoning and the LockResult/TryLockResult types are those of the
+ * inner RwLock.
  */
-struct ThreadSafeCache {
-    // RwLock allows multiple readers or one writer
-    cache: RwLock<HashMap<String, String>>,
+struct EscalatingRwLock<T> {
+    lock: RwLock<T>,
+    max_wait: Option<Duration>,
+    // Number of escalated writers; new readers wait while it is non-zero
+    gate: Mutex<usize>,
+    // Signalled whenever a guard is released or the gate opens
+    gate_changed: Condvar,
 }
 
-impl ThreadSafeCache {
+impl<T> EscalatingRwLock<T> {
     /**
-     * Create a new empty cache
+     * A lock without escalation, behaving like RwLock
      */
-    fn new() -> Self {
-        ThreadSafeCache {
-            cache: RwLock::new(HashMap::new()),
+    fn new(value: T) -> Self {
+        EscalatingRwLock {
+            lock: RwLock::new(value),
+            max_wait: None,
+            gate: Mutex::new(0),
+            gate_changed: Condvar::new(),
         }
     }
 
     /**
-     * Read operation - allows multiple concurrent readers
-     * 
-     * Uses read(): multiple threads can hold read locks simultaneously
-     * as long as no writer holds a write lock
+     * Acquire shared access, waiting at the gate while a writer is escalated
+     */
+    fn read(&self) -> LockResult<EscalatingReadGuard<'_, T>> {
+        if self.max_wait.is_some() {
+            let mut escalated = self.gate.lock().unwrap_or_else(PoisonError::into_inner);
+            while *escalated > 0 {
+                escalated = self.gate_changed.wait(escalated).unwrap_or_else(PoisonError::into_inner);
+            }
+        }
+        map_lock_result(self.lock.read(), |guard| EscalatingReadGuard { guard: Some(guard), lock: self })
+    }
+
+    /**
+     * Shared access without blocking; an escalated writer counts as WouldBlock
+     */
+    fn try_read(&self) -> TryLockResult<EscalatingReadGuard<'_, T>> {
+        if self.max_wait.is_some() && *self.gate.lock().unwrap_or_else(PoisonError::into_inner) > 0 {
+            return Err(TryLockError::WouldBlock);
+        }
+        match self.lock.try_read() {
+            Ok(guard) => Ok(EscalatingReadGuard { guard: Some(guard), lock: self }),
+            Err(TryLockError::Poisoned(e)) => Err(TryLockError::Poisoned(PoisonError::new(EscalatingReadGuard {
+                guard: Some(e.into_inner()),
+                lock: self,
+            }))),
+            Err(TryLockError::WouldBlock) => Err(TryLockError::WouldBlock),
+        }
+    }
+
+    /**
+     * Acquire exclusive access
      * 
-     * @param key The key to look up
-     * @return The value if found, otherwise "Not found"
+     * With escalation enabled the writer never blocks inside the RwLock,
+     * where it could hold back readers early; it retries whenever a guard
+     * is released and closes the gate once it has waited `max_wait`.
      */
-    fn read(&self, key: &str) -> String {
-        // Acquire read lock - multiple readers can hold this simultaneously
-        // .unwrap() panics if lock is poisoned (a thread panicked while holding lock)
-        let cache = self.cache.read().unwrap();
-        
-        // Look up the key and return cloned value or "Not found"
-        cache.get(key)
-            .map(|v| v.clone())
-            .unwrap_or_else(|| "Not found".to_string())
+    fn write(&self) -> LockResult<EscalatingWriteGuard<'_, T>> {
+        let max_wait = match self.max_wait {
+            Some(max_wait) => max_wait,
+            None => {
+                return map_lock_result(self.lock.write(), |guard| EscalatingWriteGuard { guard: Some(guard), lock: self });
+            }
+        };
         
-        // Read lock automatically released when 'cache' goes out of scope (RAII)
+        let start = Instant::now();
+        let mut is_escalated = false;
+        let mut escalated = self.gate.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            let acquired = match self.lock.try_write() {
+                Ok(guard) => Some(Ok(guard)),
+                Err(TryLockError::Poisoned(e)) => Some(Err(e)),
+                Err(TryLockError::WouldBlock) => None,
+            };
+            if let Some(result) = acquired {
+                if is_escalated {
+                    *escalated -= 1;
+                    self.gate_changed.notify_all();
+                }
+                return map_lock_result(result, |guard| EscalatingWriteGuard { guard: Some(guard), lock: self });
+            }
+            
+            let waited = start.elapsed();
+            if !is_escalated && waited >= max_wait {
+                // Close the gate: no new readers until this writer is in
+                is_escalated = true;
+                *escalated += 1;
+            }
+            escalated = if is_escalated {
+                self.gate_changed.wait(escalated).unwrap_or_else(PoisonError::into_inner)
+            } else {
+                self.gate_changed
+                    .wait_timeout(escalated, max_wait - waited)
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .0
+            };
+        }
     }
 
     /**
-     * Write operation - exclusive access required
+     * Whether a thread panicked while holding the lock
+     */
+    fn is_poisoned(&self) -> bool {
+        self.lock.is_poisoned()
+    }
+
+    /**
+     * Mutable access through a unique reference, no locking needed
+     */
+    fn get_mut(&mut self) -> LockResult<&mut T> {
+        self.lock.get_mut()
+    }
+
+    /**
+     * Called when a guard is released so waiting writers can retry
+     */
+    fn released(&self) {
+        if self.max_wait.is_some() {
+            let _gate = self.gate.lock().unwrap_or_else(PoisonError::into_inner);
+            self.gate_changed.notify_all();
+        }
+    }
+}
+
+/**
+ * Apply `f` to the guard of a LockResult, preserving the poisoned state
+ */
+fn map_lock_result<G, H, F: FnOnce(G) -> H>(result: LockResult<G>, f: F) -> LockResult<H> {
+    match result {
+        Ok(guard) => Ok(f(guard)),
+        Err(e) => Err(PoisonError::new(f(e.into_inner()))),
+    }
+}
+
+/**
+ * Shared guard of an EscalatingRwLock
+ * 
+ * The inner guard is an Option so that Drop can release the RwLock
+ * before waking writers; waking them first would let one retry, fail,
+ * and go back to sleep with no further wake-up coming.
+ */
+struct EscalatingReadGuard<'a, T> {
+    guard: Option<RwLockReadGuard<'a, T>>,
+    lock: &'a EscalatingRwLock<T>,
+}
+
+impl<T> Deref for EscalatingReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard.as_ref().expect("guard is only taken in drop")
+    }
+}
+
+impl<T> Drop for EscalatingReadGuard<'_, T> {
+    fn drop(&mut self) {
+        self.guard.take();
+        self.lock.released();
+    }
+}
+
+/**
+ * Exclusive guard of an EscalatingRwLock, see EscalatingReadGuard
+ */
+struct EscalatingWriteGuard<'a, T> {
+    guard: Option<RwLockWriteGuard<'a, T>>,
+    lock: &'a EscalatingRwLock<T>,
+}
+
+impl<T> Deref for EscalatingWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.guard.as_ref().expect("guard is only taken in drop")
+    }
+}
+
+impl<T> DerefMut for EscalatingWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        self.guard.as_mut().expect("guard is only taken in drop")
+    }
+}
+
+impl<T> Drop for EscalatingWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        self.guard.take();
+        self.lock.released();
+    }
+}
+
+/**
+ * Result of a non-blocking probe of the cache's lock
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Health {
+    // The lock could be acquired for reading
+    Healthy,
+    // A thread panicked while holding the write lock
+    Poisoned,
+    // A writer currently holds (or is queued for) the lock
+    Contended,
+}
+
+/**
+ * Outcome of a non-blocking read
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TryReadResult<V> {
+    // A writer holds (or is escalated for) the lock, nothing was looked up
+    Locked,
+    // The lock was free and the key is absent or expired
+    Missing,
+    // The lock was free and the key is present
+    Found(V),
+}
+
+/**
+ * How a cache behaves once it holds its maximum number of entries
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CapacityMode {
+    // No limit, the map grows as needed
+    Unbounded,
+    // Inserting a new key into a full cache evicts the policy's victim
+    Evict(usize),
+    // Inserting a new key into a full cache is refused
+    RejectOnFull(usize),
+}
+
+/**
+ * Errors reported by the fallible (_checked) cache operations
+ */
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CacheError {
+    // The cache is full and configured to reject rather than evict,
+    // or every entry of an evicting cache is pinned
+    CapacityExceeded,
+    // Gave up waiting for another thread's in-flight load
+    Timeout,
+    // The validator refused the entry, with its reason
+    Invalid(String),
+    // An Interrupter the cache is registered with was triggered
+    Interrupted,
+    // A thread panicked while holding the cache lock
+    Poisoned,
+}
+
+impl fmt::Display for CacheError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CacheError::CapacityExceeded => write!(f, "cache capacity exceeded"),
+            CacheError::Timeout => write!(f, "timed out waiting for an in-flight load"),
+            CacheError::Invalid(reason) => write!(f, "invalid entry: {}", reason),
+            CacheError::Interrupted => write!(f, "interrupted"),
+            CacheError::Poisoned => write!(f, "cache lock poisoned by a panicking thread"),
+        }
+    }
+}
+
+impl std::error::Error for CacheError {}
+
+/**
+ * Shared shutdown token for blocking cache operations
+ * 
+ * Caches are registered with the token; a single trigger() wakes every
+ * thread blocked in take_blocking_checked, wait_for_checked or waiting on another thread's
+ * load in get_or_load, across all registered caches, and those calls
+ * return Err(Interrupted). Interruption is permanent: later blocking
+ * calls on those caches fail immediately instead of waiting.
+ * 
+ * Clones share the same token. Only weak references to the caches are
+ * kept, so registering does not keep a cache alive.
+ */
+#[derive(Clone, Default)]
+struct Interrupter {
+    inner: Arc<InterrupterInner>,
+}
+
+#[derive(Default)]
+struct InterrupterInner {
+    triggered: AtomicBool,
+    caches: Mutex<Vec<Weak<dyn Interruptible>>>,
+}
+
+// What an Interrupter needs from a cache, whatever its key and value types
+trait Interruptible: Send + Sync {
+    fn interrupt(&self);
+}
+
+impl Interrupter {
+    fn new() -> Self {
+        Interrupter::default()
+    }
+
+    /**
+     * Have trigger() interrupt `cache`
      * 
-     * Uses write(): only one thread can hold this lock
-     * Blocks all readers and other writers until complete
+     * A cache registered after the trigger is interrupted right away.
+     */
+    fn register<K, V>(&self, cache: &Arc<ThreadSafeCache<K, V>>)
+    where
+        ThreadSafeCache<K, V>: Interruptible + 'static,
+    {
+        let mut caches = self.inner.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        caches.retain(|weak| weak.strong_count() > 0);
+        let weak: Weak<ThreadSafeCache<K, V>> = Arc::downgrade(cache);
+        caches.push(weak);
+        // Checked under the list lock: a concurrent trigger either sees
+        // this cache in the list or has already set the flag
+        if self.is_triggered() {
+            cache.interrupt();
+        }
+    }
+
+    /**
+     * Interrupt every registered cache
+     */
+    fn trigger(&self) {
+        self.inner.triggered.store(true, Ordering::SeqCst);
+        let caches = self.inner.caches.lock().unwrap_or_else(PoisonError::into_inner);
+        for cache in caches.iter().filter_map(Weak::upgrade) {
+            cache.interrupt();
+        }
+    }
+
+    fn is_triggered(&self) -> bool {
+        self.inner.triggered.load(Ordering::SeqCst)
+    }
+}
+
+/**
+ * Point-in-time view of how often entries are replaced
+ * 
+ * churn_ratio is evictions / inserts_new: near 0 the working set fits,
+ * near 1 almost every new key pushes another one out
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct ChurnStats {
+    inserts_new: u64,
+    inserts_overwrite: u64,
+    evictions: u64,
+    churn_ratio: f64,
+}
+
+// Number of independent slots in a ShardedCounter
+const COUNTER_SHARDS: usize = 16;
+
+// Hands out shard indices to threads round-robin
+static NEXT_SHARD: AtomicUsize = AtomicUsize::new(0);
+
+thread_local! {
+    // Each thread always bumps the same shard
+    static SHARD: usize = NEXT_SHARD.fetch_add(1, Ordering::Relaxed) % COUNTER_SHARDS;
+}
+
+/**
+ * An AtomicU64 alone on its cache line
+ * 
+ * Without the padding neighbouring shards would share a line and every
+ * increment would still bounce it between cores (false sharing)
+ */
+#[repr(align(64))]
+struct PaddedCounter(AtomicU64);
+
+/**
+ * Counter split across per-thread shards
+ * 
+ * A single AtomicU64 bumped by many threads is one contended cache line.
+ * Here each thread increments its own shard and the total is summed on
+ * read-out, so increments scale while reads get slightly more expensive.
+ */
+struct ShardedCounter {
+    shards: [PaddedCounter; COUNTER_SHARDS],
+}
+
+impl ShardedCounter {
+    fn new() -> Self {
+        ShardedCounter {
+            shards: std::array::from_fn(|_| PaddedCounter(AtomicU64::new(0))),
+        }
+    }
+
+    /**
+     * Add `n` to the calling thread's shard
+     */
+    fn add(&self, n: u64) {
+        let shard = SHARD.with(|shard| *shard);
+        self.shards[shard].0.fetch_add(n, Ordering::Relaxed);
+    }
+
+    /**
+     * Set every shard back to zero
      * 
-     * @param key The key to insert/update
-     * @param value The value to store
+     * Increments racing with the reset may survive it
      */
-    fn write(&self, key: String, value: String) {
-        // Acquire write lock - exclusive access, blocks all other threads
-        // .unwrap() panics if lock is poisoned
-        let mut cache = self.cache.write().unwrap();
-        
-        cache.insert(key, value);
-        
-        // Write lock automatically released when 'cache' goes out of scope (RAII)
+    fn reset(&self) {
+        for shard in &self.shards {
+            shard.0.store(0, Ordering::Relaxed);
+        }
     }
 
     /**
-     * Size query - read operation with shared access
+     * Total over all shards
      * 
-     * @return Current number of entries in cache
+     * Not a single atomic snapshot: increments racing with the sum may
+     * or may not be included
      */
-    fn size(&self) -> usize {
-        // Acquire read lock - can be called concurrently with other reads
-        let cache = self.cache.read().unwrap();
-        cache.len()
+    fn sum(&self) -> u64 {
+        self.shards.iter().map(|shard| shard.0.load(Ordering::Relaxed)).sum()
     }
 }
 
-fn main() {
-    // Arc (Atomic Reference Counting) allows shared ownership across threads
-    let cache = Arc::new(ThreadSafeCache::new());
-    
-    // Mutex to protect stdout from interleaved output
-    let cout_mutex = Arc::new(Mutex::new(()));
+/**
+ * Churn counters, updated while the write lock is held
+ * 
+ * Atomics let churn() read them without taking the cache lock
+ */
+struct ChurnCounters {
+    inserts_new: ShardedCounter,
+    inserts_overwrite: ShardedCounter,
+    evictions: ShardedCounter,
+}
+
+impl ChurnCounters {
+    fn new() -> Self {
+        ChurnCounters {
+            inserts_new: ShardedCounter::new(),
+            inserts_overwrite: ShardedCounter::new(),
+            evictions: ShardedCounter::new(),
+        }
+    }
+
+    fn snapshot(&self) -> ChurnStats {
+        let inserts_new = self.inserts_new.sum();
+        let evictions = self.evictions.sum();
+        ChurnStats {
+            inserts_new,
+            inserts_overwrite: self.inserts_overwrite.sum(),
+            evictions,
+            churn_ratio: if inserts_new == 0 { 0.0 } else { evictions as f64 / inserts_new as f64 },
+        }
+    }
+}
+
+/**
+ * Point-in-time view of how reads and writes have gone
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct CacheStats {
+    hits: u64,
+    misses: u64,
+    writes: u64,
+}
+
+/**
+ * Access counters behind CacheStats
+ * 
+ * Bumped with Relaxed increments on sharded atomics, so counting a read
+ * neither needs the write lock nor makes concurrent readers contend on
+ * one cache line
+ */
+struct StatsCounters {
+    hits: ShardedCounter,
+    misses: ShardedCounter,
+    writes: ShardedCounter,
+}
+
+impl StatsCounters {
+    fn new() -> Self {
+        StatsCounters {
+            hits: ShardedCounter::new(),
+            misses: ShardedCounter::new(),
+            writes: ShardedCounter::new(),
+        }
+    }
+
+    fn snapshot(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.sum(),
+            misses: self.misses.sum(),
+            writes: self.writes.sum(),
+        }
+    }
+
+    fn reset(&self) {
+        self.hits.reset();
+        self.misses.reset();
+        self.writes.reset();
+    }
+}
+
+/**
+ * Expiration settings for a write
+ * 
+ * The time-to-live counts from the write, the time-to-idle from the most
+ * recent read. An entry with both expires as soon as either runs out.
+ */
+#[derive(Debug, Clone, Copy, Default)]
+struct Expiry {
+    ttl: Option<Duration>,
+    tti: Option<Duration>,
+}
+
+/**
+ * A cached value together with its eviction bookkeeping
+ * 
+ * The last access time is atomic so that readers, which only hold the
+ * shared lock, can still restart the idle clock; it is stored as
+ * nanoseconds since the cache's epoch because an Instant cannot be
+ * updated atomically. Recency and frequency for eviction are tracked by
+ * the cache's EvictionPolicy, not here.
+ * The version changes on every modification of the entry, for
+ * optimistic read-then-compare-and-write updates.
+ */
+struct Entry<V> {
+    value: V,
+    version: u64,
+    // Version the key was first inserted with; unlike `version` it is
+    // kept on overwrite, giving the insertion order
+    inserted: u64,
+    // Cache generation current when the entry was last written
+    generation: u64,
+    expires_at: Option<Instant>,
+    tti: Option<Duration>,
+    last_access: AtomicU64,
+}
+
+impl<V> Entry<V> {
+    fn new(value: V, version: u64, generation: u64) -> Self {
+        Entry {
+            value,
+            version,
+            inserted: version,
+            generation,
+            expires_at: None,
+            tti: None,
+            last_access: AtomicU64::new(0),
+        }
+    }
 
     /**
-     * Writer thread - populates cache with 5 key-value pairs
-     * Writes every 100ms
+     * Replace the expiration settings, starting both clocks now
      */
-    let cache_writer = Arc::clone(&cache);
-    let cout_writer = Arc::clone(&cout_mutex);
-    let writer = thread::spawn(move || {
-        for i in 0..5 {
-            // Exclusive write - blocks all readers during this operation
-            cache_writer.write(
-                format!("key{}", i),
-                format!("value{}", i)
-            );
-            
-            // Protect console output to prevent garbled text
-            {
-                let _lock = cout_writer.lock().unwrap();
-                println!("Writer {}: {}", i, i);
-            } // cout_mutex released here
-            
-            // Sleep to simulate real work and allow readers to interleave
-            thread::sleep(Duration::from_millis(100));
-        }
-    });
+    fn set_expiry(&mut self, expiry: Expiry, epoch: Instant) {
+        let now = Instant::now();
+        self.expires_at = expiry.ttl.map(|ttl| now + ttl);
+        self.tti = expiry.tti;
+        *self.last_access.get_mut() = nanos_since(epoch, now);
+    }
 
     /**
-     * Reader threads - 3 concurrent readers
-     * Each attempts to read keys 0-4 (cycling), every 50ms
-     * 
-     * Note: Readers run faster (50ms) than writer (100ms),
-     * so early reads may find "Not found" for keys not yet written
-     */
-    let mut readers = vec![];
-    for i in 0..3 {
-        let cache_reader = Arc::clone(&cache);
-        let cout_reader = Arc::clone(&cout_mutex);
-        
-        let reader = thread::spawn(move || {
-            for j in 0..10 {
-                // Shared read - can run concurrently with other reads
-                // but will block if writer holds write lock
-                let value = cache_reader.read(&format!("key{}", j % 5));
-                
-                // Protect console output
-                {
-                    let _lock = cout_reader.lock().unwrap();
-                    println!("Reader {}: {}", i, value);
-                } // cout_mutex released here
-                
-                // Sleep shorter than writer, demonstrating race conditions
-                thread::sleep(Duration::from_millis(50));
-            }
+     * Record a read: restarts the idle clock
+     */
+    fn touch(&self, epoch: Instant) {
+        if self.tti.is_some() {
+            self.last_access.store(nanos_since(epoch, Instant::now()), Ordering::Relaxed);
+        }
+    }
+
+    fn is_expired(&self, epoch: Instant) -> bool {
+        if self.expires_at.is_none() && self.tti.is_none() {
+            return false;
+        }
+        let now = Instant::now();
+        let ttl_expired = self.expires_at.is_some_and(|at| now >= at);
+        let tti_expired = self.tti.is_some_and(|tti| {
+            let last_access = epoch + Duration::from_nanos(self.last_access.load(Ordering::Relaxed));
+            now.duration_since(last_access) > tti
         });
-        
-        readers.push(reader);
+        ttl_expired || tti_expired
     }
+}
 
-    // Wait for writer to complete
-    writer.join().unwrap();
-    
-    // Wait for all readers to complete
-    for reader in readers {
-        reader.join().unwrap();
+fn nanos_since(epoch: Instant, now: Instant) -> u64 {
+    now.duration_since(epoch).as_nanos() as u64
+}
+
+/**
+ * Everything protected by the cache's lock
+ * 
+ * For evicting caches the keys are also tracked by an EvictionPolicy,
+ * which names the victim when an entry must make room. It lives under
+ * the same lock as the map so the two always hold the same keys.
+ * Pinned keys are passed over as victims, and never expire. Keys written
+ * since the last flush are tracked as dirty.
+ * 
+ * Expired entries are removed lazily: lookups treat them as absent, and
+ * they are dropped when their key is written again or on purge_expired.
+ * Entries written before the last bump_generation count as expired.
+ */
+struct CacheState<K, V> {
+    map: HashMap<K, Entry<V>>,
+    policy: Option<Box<dyn EvictionPolicy<K>>>,
+    pinned: HashSet<K>,
+    dirty: HashSet<K>,
+    // Versions come from one counter, so a key that is removed and
+    // written again never reuses an old version
+    next_version: u64,
+    // Reference point for the entries' last_access times
+    epoch: Instant,
+    // Atomic so that bump_generation only needs the shared lock
+    generation: AtomicU64,
+}
+
+impl<K: Eq + Hash, V> CacheState<K, V> {
+    fn new(policy: Option<Box<dyn EvictionPolicy<K>>>) -> Self {
+        CacheState {
+            map: HashMap::new(),
+            policy,
+            pinned: HashSet::new(),
+            dirty: HashSet::new(),
+            next_version: 1,
+            epoch: Instant::now(),
+            generation: AtomicU64::new(0),
+        }
+    }
+
+    /**
+     * The entry for `key`, unless it has expired
+     */
+    fn live<Q>(&self, key: &Q) -> Option<&Entry<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.live_with_key(key).map(|(_, entry)| entry)
     }
+
+    /**
+     * Like live, also returning the map's own copy of the key
+     */
+    fn live_with_key<Q>(&self, key: &Q) -> Option<(&K, &Entry<V>)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.map.get_key_value(key).filter(|(key, entry)| self.is_live(*key, entry))
+    }
+
+    /**
+     * Record a read of a live entry, for its idle clock and for the policy
+     */
+    fn touch(&self, key: &K, entry: &Entry<V>) {
+        entry.touch(self.epoch);
+        if let Some(policy) = &self.policy {
+            policy.on_access(key);
+        }
+    }
+
+    fn is_live(&self, key: &K, entry: &Entry<V>) -> bool {
+        // Pinning protects against expiry, not against a bumped generation
+        entry.generation == self.current_generation() && (!entry.is_expired(self.epoch) || self.pinned.contains(key))
+    }
+
+    fn current_generation(&self) -> u64 {
+        self.generation.load(Ordering::Relaxed)
+    }
+
+    /**
+     * Drop an expired entry so that its key can be written as a new one
+     */
+    fn purge_if_expired<Q>(&mut self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        if self.map.contains_key(key) && self.live(key).is_none() {
+            self.remove_entry(key);
+        }
+    }
+
+    fn bump_version(&mut self) -> u64 {
+        let version = self.next_version;
+        self.next_version += 1;
+        version
+    }
+
+    /**
+     * Ask the policy for a victim that is not pinned
+     * 
+     * The policy passes over pinned keys where they are, so pinning does
+     * not change how a key is evicted once it is unpinned.
+     * 
+     * @return The victim, already forgotten by the policy, or None if
+     *         every key is pinned or the cache does not evict
+     */
+    fn evict_victim(&mut self) -> Option<K> {
+        let policy = self.policy.as_ref()?;
+        let pinned = &self.pinned;
+        policy.evict_victim(&|key: &K| pinned.contains(key))
+    }
+
+    /**
+     * Remove a key from the map and from the eviction policy
+     * 
+     * @return The removed key and entry, or None if the key was absent
+     */
+    fn remove_entry<Q>(&mut self, key: &Q) -> Option<(K, Entry<V>)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let (owned_key, entry) = self.map.remove_entry(key)?;
+        self.dirty.remove(key);
+        if let Some(policy) = &self.policy {
+            policy.on_remove(&owned_key);
+        }
+        Some((owned_key, entry))
+    }
+}
+
+/**
+ * Concurrent bloom filter over hashable keys
+ * 
+ * A fixed array of bits stored in AtomicU64 words; insert sets bits with
+ * fetch_or and lookups only load, so neither takes a lock and any number
+ * of threads may use the filter at once. Bits are only ever set, never
+ * cleared, so keys cannot be removed.
+ * 
+ * might_contain has no false negatives: if a key was inserted it always
+ * answers true. It may answer true for a key that was never inserted
+ * (a false positive), at roughly the rate the filter was sized for.
+ */
+struct BloomFilter {
+    bits: Vec<AtomicU64>,
+    num_bits: u64,
+    num_hashes: u32,
+}
+
+impl BloomFilter {
+    /**
+     * Size a filter for `expected_items` keys at `false_positive_rate`
+     * 
+     * Uses the standard formulas m = -n ln(p) / ln(2)^2 bits and
+     * k = m/n ln(2) hash functions.
+     * 
+     * @param expected_items Number of keys that will be inserted
+     * @param false_positive_rate Target rate in (0, 1), e.g. 0.01
+     */
+    fn new(expected_items: usize, false_positive_rate: f64) -> Self {
+        assert!(
+            false_positive_rate > 0.0 && false_positive_rate < 1.0,
+            "false positive rate must be in (0, 1)"
+        );
+        let n = expected_items.max(1) as f64;
+        let ln2 = std::f64::consts::LN_2;
+        let num_bits = ((-n * false_positive_rate.ln() / (ln2 * ln2)).ceil() as u64).max(64);
+        let num_hashes = ((num_bits as f64 / n * ln2).round() as u32).max(1);
+        let words = num_bits.div_ceil(64) as usize;
+        BloomFilter {
+            bits: (0..words).map(|_| AtomicU64::new(0)).collect(),
+            num_bits,
+            num_hashes,
+        }
+    }
+
+    /**
+     * Record `key` as present
+     */
+    fn insert<T: Hash + ?Sized>(&self, key: &T) {
+        for bit in self.bit_indexes(key) {
+            self.bits[(bit / 64) as usize].fetch_or(1 << (bit % 64), Ordering::Relaxed);
+        }
+    }
+
+    /**
+     * false means `key` was definitely never inserted, true means it may have been
+     */
+    fn might_contain<T: Hash + ?Sized>(&self, key: &T) -> bool {
+        self.bit_indexes(key)
+            .all(|bit| self.bits[(bit / 64) as usize].load(Ordering::Relaxed) & (1 << (bit % 64)) != 0)
+    }
+
+    // Double hashing: the k indexes are h1 + i * h2, from two seeded hashes
+    fn bit_indexes<T: Hash + ?Sized>(&self, key: &T) -> impl Iterator<Item = u64> {
+        let hash_with = |seed: u64| {
+            let mut hasher = DefaultHasher::new();
+            seed.hash(&mut hasher);
+            key.hash(&mut hasher);
+            hasher.finish()
+        };
+        let (h1, h2) = (hash_with(0), hash_with(1) | 1);
+        let num_bits = self.num_bits;
+        (0..u64::from(self.num_hashes)).map(move |i| h1.wrapping_add(i.wrapping_mul(h2)) % num_bits)
+    }
+}
+
+// Checks an entry before it is written, Err carries the reason for refusal
+type Validator<K, V> = Box<dyn Fn(&K, &V) -> Result<(), String> + Send + Sync>;
+
+/**
+ * Thread-safe cache using the Reader-Writer pattern
+ * 
+ * Uses RwLock to allow:
+ * - Multiple simultaneous readers (shared access)
+ * - Exclusive writer access (blocks all readers and other writers)
+ * 
+ * Optionally bounded: once full, inserting a new key either evicts an
+ * entry chosen by an EvictionPolicy or is rejected. with_capacity
+ * evicts the least recently used entry; CLOCK, a cheaper approximation
+ * where a read only sets a bit instead of reordering a list, is opt-in
+ * through with_clock_eviction or with_policy.
+ * 
+ * The RwLock is wrapped in an EscalatingRwLock, which by default behaves
+ * exactly like it; with_writer_escalation bounds how long writers can be
+ * starved by readers.
+ * 
+ * Generic over the key and value types. Lookups accept any borrowed
+ * form of the key, as HashMap::get does, so a ThreadSafeCache<String, V>
+ * can be read with a &str. Values are cloned out on every read; wrap
+ * large values in Arc to make that cheap.
+ * 
+ * Wrapped in Arc for shared ownership across threads
+ */
+struct ThreadSafeCache<K, V> {
+    // RwLock allows multiple readers or one writer
+    cache: EscalatingRwLock<CacheState<K, V>>,
+    // Entry limit and what happens when it is reached
+    capacity: CapacityMode,
+    // Signalled after every write so blocked takers can re-check
+    // (a Condvar needs a Mutex, it cannot wait on the lock itself)
+    change_lock: Mutex<()>,
+    changed: Condvar,
+    churn: ChurnCounters,
+    stats: StatsCounters,
+    // Keys whose loader is currently running in get_or_load, and the
+    // Condvar signalled whenever one of those loads finishes
+    loads: Mutex<HashSet<K>>,
+    load_done: Condvar,
+    // How long get_or_load waits for another thread's load, None = forever
+    coalesce_timeout: Option<Duration>,
+    // Per-key subscribers registered with watch_key
+    watchers: Mutex<HashMap<K, Vec<Sender<V>>>>,
+    validator: Option<Validator<K, V>>,
+    // Keys the backing source holds, consulted by get_or_load on a miss
+    bloom: Option<Arc<BloomFilter>>,
+    // Set by an Interrupter; blocking operations then return Err(Interrupted)
+    interrupted: AtomicBool,
+    // Threads running read_async, started by its first call
+    read_pool: OnceLock<ReadPool>,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> ThreadSafeCache<K, V> {
+    /**
+     * Create a new empty cache
+     */
+    fn new() -> Self {
+        Self::with_mode(CapacityMode::Unbounded, None)
+    }
+
+    /**
+     * Create a cache holding at most `max` entries, evicting the least
+     * recently used
+     * 
+     * A write of a new key into a full cache first evicts the entry whose
+     * last read or write is oldest, so size() never exceeds `max`.
+     * Recency is tracked by the Lru policy behind its own Mutex: read()
+     * still only takes the shared lock on the cache, but every hit briefly
+     * locks that Mutex to move the key to the front, so hits on different
+     * keys serialise there. Use with_clock_eviction when that matters.
+     * 
+     * @param max Maximum number of entries, must be greater than zero
+     */
+    fn with_capacity(max: usize) -> Self
+    where
+        K: Send + 'static,
+    {
+        Self::with_policy(max, Lru::new())
+    }
+
+    /**
+     * Create an unbounded cache whose lock prefers writers
+     * 
+     * Writer escalation with no grace period: as soon as a writer finds
+     * the lock taken, new readers are held back until it is in, so a write
+     * completes once the readers already inside finish, however steady
+     * the read load. Reads pay an extra mutex check at the gate.
+     */
+    fn with_fairness() -> Self {
+        Self::new().with_writer_escalation(Duration::ZERO)
+    }
+
+    /**
+     * Create a cache holding at most `capacity` entries, evicting with CLOCK
+     * 
+     * @param capacity Maximum number of entries, must be greater than zero
+     */
+    fn with_clock_eviction(capacity: usize) -> Self
+    where
+        K: Send + Sync + 'static,
+    {
+        Self::with_policy(capacity, Clock::new())
+    }
+
+    /**
+     * Create a cache holding at most `capacity` entries, evicting with `policy`
+     * 
+     * Only which key is evicted depends on the policy; reads, writes,
+     * expiry and pinning behave the same whichever one is chosen.
+     * 
+     * @param capacity Maximum number of entries, must be greater than zero
+     * @param policy Chooses the entry to evict, e.g. Lru, Lfu or Clock
+     */
+    fn with_policy<P: EvictionPolicy<K> + 'static>(capacity: usize, policy: P) -> Self {
+        assert!(capacity > 0, "cache capacity must be greater than zero");
+        Self::with_mode(CapacityMode::Evict(capacity), Some(Box::new(policy)))
+    }
+
+    /**
+     * Create a cache holding at most `capacity` entries that never evicts
+     * 
+     * Once full, writes of new keys are refused (write_checked reports
+     * CapacityExceeded) while overwrites of existing keys still succeed.
+     * 
+     * @param capacity Maximum number of entries, must be greater than zero
+     */
+    fn with_reject_on_full(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be greater than zero");
+        Self::with_mode(CapacityMode::RejectOnFull(capacity), None)
+    }
+
+    /**
+     * Refuse entries that fail `validator`
+     * 
+     * The validator sees every caller-supplied key/value pair in write,
+     * write_checked, write_if_version and write_merge before the write
+     * lock is taken, so a slow check does not block readers. The results
+     * of merge and update closures are not re-validated.
+     * 
+     * @param validator Returns Err(reason) to reject an entry
+     */
+    fn with_validator<F>(mut self, validator: F) -> Self
+    where
+        F: Fn(&K, &V) -> Result<(), String> + Send + Sync + 'static,
+    {
+        self.validator = Some(Box::new(validator));
+        self
+    }
+
+    /**
+     * Build an unbounded cache from key/value pairs in one go
+     * 
+     * The map is pre-sized for `capacity` entries so seeding a large cache
+     * does not rehash repeatedly, and the pairs are inserted through
+     * get_mut(): the cache is not shared yet, so no lock is taken.
+     * Later pairs overwrite earlier ones with the same key.
+     * 
+     * @param iter The initial entries
+     * @param capacity Expected number of entries (a size hint, not a limit)
+     */
+    fn from_iter_with_capacity<I: IntoIterator<Item = (K, V)>>(iter: I, capacity: usize) -> Self {
+        let mut cache = Self::new();
+        let state = cache.cache.get_mut().unwrap();
+        state.map.reserve(capacity);
+        
+        for (key, value) in iter {
+            let version = state.bump_version();
+            let mut entry = Entry::new(value, version, state.current_generation());
+            let counter = match state.map.get(&key) {
+                Some(old) => {
+                    entry.inserted = old.inserted;
+                    &cache.churn.inserts_overwrite
+                }
+                None => &cache.churn.inserts_new,
+            };
+            state.map.insert(key, entry);
+            counter.add(1);
+        }
+        cache
+    }
+
+    fn with_mode(capacity: CapacityMode, policy: Option<Box<dyn EvictionPolicy<K>>>) -> Self {
+        ThreadSafeCache {
+            cache: EscalatingRwLock::new(CacheState::new(policy)),
+            capacity,
+            change_lock: Mutex::new(()),
+            changed: Condvar::new(),
+            churn: ChurnCounters::new(),
+            stats: StatsCounters::new(),
+            loads: Mutex::new(HashSet::new()),
+            load_done: Condvar::new(),
+            coalesce_timeout: None,
+            watchers: Mutex::new(HashMap::new()),
+            validator: None,
+            bloom: None,
+            interrupted: AtomicBool::new(false),
+            read_pool: OnceLock::new(),
+        }
+    }
+
+    /**
+     * Escalate writers that have waited longer than `max_wait`
+     * 
+     * Readers keep priority while writers wait less than `max_wait`; after
+     * that new readers are held back until the writer has acquired the
+     * lock, so a write completes in roughly `max_wait` plus the time the
+     * readers already inside need to finish, even under a constant reader
+     * stream.
+     * 
+     * @param max_wait How long a writer waits before being escalated
+     */
+    fn with_writer_escalation(mut self, max_wait: Duration) -> Self {
+        self.cache.max_wait = Some(max_wait);
+        self
+    }
+
+    /**
+     * Put a bloom filter of the source's keys in front of get_or_load
+     * 
+     * On a cache miss get_or_load first asks the filter; a key it has
+     * definitely never seen returns Ok(None) without calling the loader.
+     * A false positive just means the loader runs and finds nothing, as
+     * it would without the filter. Because the filter has no false
+     * negatives, no key that was inserted is ever skipped, so every key
+     * the source holds must be inserted. The filter is shared: the caller
+     * can keep inserting keys as the source grows.
+     * 
+     * @param filter Filter populated with the keys the loader can find
+     */
+    fn with_bloom_filter(mut self, filter: Arc<BloomFilter>) -> Self {
+        self.bloom = Some(filter);
+        self
+    }
+
+    /**
+     * Bound how long get_or_load waits on another thread's load
+     * 
+     * @param timeout Maximum wait before a waiter gets Err(Timeout)
+     */
+    fn with_coalesce_timeout(mut self, timeout: Duration) -> Self {
+        self.coalesce_timeout = Some(timeout);
+        self
+    }
+
+    /**
+     * Read operation - allows multiple concurrent readers
+     * 
+     * Uses read(): multiple threads can hold read locks simultaneously
+     * as long as no writer holds a write lock
+     * 
+     * A hit counts as an access for the eviction policy (for CLOCK it
+     * sets the reference bit, giving the entry a second chance during the
+     * next sweep) and restarts its time-to-idle.
+     * Expired entries are reported as None.
+     * 
+     * Counts as a hit or a miss in stats().
+     * 
+     * Never fails: a lock poisoned by a panicking writer is recovered
+     * and read anyway, see try_read_checked for a read that reports it.
+     * 
+     * @param key The key to look up
+     * @return A clone of the value if found, otherwise None
+     */
+    fn read<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let value = self.read_uncounted(key);
+        match value {
+            Some(_) => self.stats.hits.add(1),
+            None => self.stats.misses.add(1),
+        }
+        value
+    }
+
+    /**
+     * read() that refuses to use a poisoned lock
+     * 
+     * A panic under the write lock may have left an entry half-updated.
+     * Instead of recovering silently like read(), this reports the poison
+     * so the caller can decide whether to trust the cache, rebuild it or
+     * fail; CacheError implements std::error::Error, so `?` works.
+     * 
+     * @param key The key to look up
+     * @return Ok(value or None), or Err(Poisoned)
+     */
+    fn try_read_checked<Q>(&self, key: &Q) -> Result<Option<V>, CacheError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let cache = self.cache.read().map_err(|_| CacheError::Poisoned)?;
+        let value = cache.live_with_key(key).map(|(key, entry)| {
+            cache.touch(key, entry);
+            entry.value.clone()
+        });
+        match value {
+            Some(_) => self.stats.hits.add(1),
+            None => self.stats.misses.add(1),
+        }
+        Ok(value)
+    }
+
+    /**
+     * read() without touching the hit/miss counters, for internal re-checks
+     */
+    fn read_uncounted<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        // Acquire read lock - multiple readers can hold this simultaneously
+        // (a poisoned lock is recovered rather than panicking, see read_state)
+        let cache = self.read_state();
+        
+        // Look up the key and return a cloned value
+        cache.live_with_key(key).map(|(key, entry)| {
+            cache.touch(key, entry);
+            entry.value.clone()
+        })
+        
+        // Read lock automatically released when 'cache' goes out of scope (RAII)
+    }
+
+    /**
+     * Read without waiting for a writer
+     * 
+     * Built on try_read() of the lock: if a writer holds it, or an
+     * escalated writer is waiting for it, this returns Locked immediately
+     * instead of blocking, so a latency-sensitive caller can fall back to
+     * a stale or default answer. Otherwise it behaves like read(),
+     * including the hit/miss counting; a Locked probe counts as neither.
+     * A poisoned lock is recovered as in read().
+     * 
+     * @param key The key to look up
+     * @return Locked, Missing or Found(value)
+     */
+    fn try_read<Q>(&self, key: &Q) -> TryReadResult<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let cache = match self.cache.try_read() {
+            Ok(guard) => guard,
+            Err(TryLockError::Poisoned(e)) => e.into_inner(),
+            Err(TryLockError::WouldBlock) => return TryReadResult::Locked,
+        };
+        
+        match cache.live_with_key(key) {
+            Some((key, entry)) => {
+                cache.touch(key, entry);
+                self.stats.hits.add(1);
+                TryReadResult::Found(entry.value.clone())
+            }
+            None => {
+                self.stats.misses.add(1);
+                TryReadResult::Missing
+            }
+        }
+    }
+
+    /**
+     * Look up several keys under a single read lock
+     * 
+     * All answers come from the same moment, so a batch written with
+     * write_many is seen either completely or not at all. Each key counts
+     * as a hit or a miss in stats().
+     * 
+     * @param keys The keys to look up
+     * @return One entry per key, in the same order: the value or None
+     */
+    fn read_many<Q>(&self, keys: &[&Q]) -> Vec<Option<V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let cache = self.read_state();
+        keys.iter()
+            .map(|key| match cache.live_with_key(*key) {
+                Some((key, entry)) => {
+                    cache.touch(key, entry);
+                    self.stats.hits.add(1);
+                    Some(entry.value.clone())
+                }
+                None => {
+                    self.stats.misses.add(1);
+                    None
+                }
+            })
+            .collect()
+    }
+
+    /**
+     * Whether `key` is present, without cloning its value
+     * 
+     * Only a shared lock is taken. Unlike read this is not an access: it
+     * does not refresh the entry for the eviction policy or its
+     * time-to-idle, and is not counted in stats().
+     * 
+     * @param key The key to check
+     * @return true if the key is present and not expired
+     */
+    fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.read_state().live(key).is_some()
+    }
+
+    /**
+     * Count the live keys starting with `prefix`, under one read lock
+     * 
+     * Scans every key, O(n); no values are cloned.
+     * 
+     * @param prefix The prefix to match, "" counts every live key
+     * @return Number of matching keys
+     */
+    fn count_prefix(&self, prefix: &str) -> usize
+    where
+        K: Borrow<str>,
+    {
+        let cache = self.read_state();
+        cache
+            .map
+            .iter()
+            .filter(|(key, entry)| (*key).borrow().starts_with(prefix) && cache.is_live(key, entry))
+            .count()
+    }
+
+    /**
+     * Read with a computed fallback that is not cached
+     * 
+     * Unlike a read-through loader, a miss calls `default` and returns its
+     * result without inserting it, so the cache is left unchanged.
+     * `default` runs after the read lock has been released.
+     * 
+     * @param key The key to look up
+     * @param default Produces the value to return on a miss
+     * @return The cached value, or default()
+     */
+    fn read_or_else<Q, F>(&self, key: &Q, default: F) -> V
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        F: FnOnce() -> V,
+    {
+        self.read(key).unwrap_or_else(default)
+    }
+
+    /**
+     * Read-through with request coalescing
+     * 
+     * On a miss exactly one thread runs `loader` for a given key; other
+     * threads missing on the same key wait for that load instead of
+     * starting their own. The loader runs without any cache lock held.
+     * A successful load is written to the cache before waiters are woken.
+     * 
+     * If a coalesce timeout is configured, a waiter whose load has not
+     * completed in time returns Err(Timeout) and may simply retry; the
+     * load itself carries on and still populates the cache. A load that
+     * returns None (the source has no such key) is not cached, so the
+     * woken waiters each try the loader themselves. If the cache is
+     * interrupted, waiters return Err(Interrupted) instead.
+     * 
+     * With a bloom filter configured, a miss on a key the filter has
+     * definitely never seen returns Ok(None) without running the loader.
+     * 
+     * @param key The key to look up
+     * @param loader Fetches the value from the backing source
+     * @return The cached or loaded value, None if the source lacks it
+     */
+    fn get_or_load<Q, F>(&self, key: &Q, loader: F) -> Result<Option<V>, CacheError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+        F: FnOnce() -> Option<V>,
+    {
+        // Fast path: shared lock only
+        if let Some(value) = self.read(key) {
+            return Ok(Some(value));
+        }
+        if let Some(bloom) = &self.bloom {
+            if !bloom.might_contain(key) {
+                // Definitely not in the source, no need to ask it
+                return Ok(None);
+            }
+        }
+        
+        let deadline = self.coalesce_timeout.map(|timeout| Instant::now() + timeout);
+        let mut loads = self.loads.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            // Re-check under the loads mutex: a load may have just finished
+            // (the miss was already counted by the fast path)
+            if let Some(value) = self.read_uncounted(key) {
+                return Ok(Some(value));
+            }
+            if !loads.contains(key) {
+                break;
+            }
+            if self.interrupted.load(Ordering::SeqCst) {
+                return Err(CacheError::Interrupted);
+            }
+            
+            loads = match deadline {
+                None => self.load_done.wait(loads).unwrap_or_else(PoisonError::into_inner),
+                Some(deadline) => {
+                    let now = Instant::now();
+                    if now >= deadline {
+                        return Err(CacheError::Timeout);
+                    }
+                    self.load_done
+                        .wait_timeout(loads, deadline - now)
+                        .unwrap_or_else(PoisonError::into_inner)
+                        .0
+                }
+            };
+        }
+        
+        // This thread is the loader for `key`
+        let in_flight = InFlight { cache: self, key: key.to_owned() };
+        loads.insert(in_flight.key.clone());
+        drop(loads);
+        
+        let value = loader();
+        if let Some(value) = &value {
+            self.write(in_flight.key.clone(), value.clone());
+        }
+        Ok(value)
+    }
+
+    /**
+     * Read-through where the loader runs under the write lock
+     * 
+     * A hit is served under the shared lock. On a miss the write lock is
+     * taken and the key looked up again, since another thread may have
+     * inserted it between the two locks; `loader` only runs if the key is
+     * still absent, so it runs at most once per key however many threads
+     * miss together. The price is that every other reader and writer
+     * waits while it runs; for slow loaders use get_or_load, which runs
+     * the loader with no lock held.
+     * 
+     * @param key The key to look up
+     * @param loader Computes the value for a missing key
+     * @return The cached value, or the loaded one (returned even if a
+     *         full reject-on-full cache or the validator refuses to store it)
+     */
+    fn get_or_insert_with<Q, F>(&self, key: &Q, loader: F) -> V
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+        F: FnOnce() -> V,
+    {
+        // Fast path: shared lock only
+        if let Some(value) = self.read(key) {
+            return value;
+        }
+        
+        let mut cache = self.write_state();
+        // Double-check: the key may have been inserted while we waited
+        if let Some((key, entry)) = cache.live_with_key(key) {
+            cache.touch(key, entry);
+            return entry.value.clone();
+        }
+        
+        let key = key.to_owned();
+        let value = loader();
+        if self.validate(&key, &value).is_ok() {
+            let _ = self.insert_locked(&mut cache, key, value.clone(), Expiry::default());
+        }
+        drop(cache);
+        
+        self.notify_changed();
+        value
+    }
+
+    /**
+     * Which of the desired keys are not cached
+     * 
+     * All keys are checked under one read lock, giving a consistent answer
+     * to drive a targeted warm-up of just the absent ones.
+     * 
+     * @param desired The keys the caller wants present
+     * @return The absent keys, in the order they were given
+     */
+    fn missing_keys<'a, Q, I>(&self, desired: I) -> Vec<K>
+    where
+        I: IntoIterator<Item = &'a Q>,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized + 'a,
+    {
+        let cache = self.read_state();
+        desired
+            .into_iter()
+            .filter(|key| cache.live(*key).is_none())
+            .map(ToOwned::to_owned)
+            .collect()
+    }
+
+    /**
+     * Read a value together with its current version
+     * 
+     * Pass the version to write_if_version later to update the key only
+     * if nobody modified it in the meantime.
+     * 
+     * @param key The key to look up
+     * @return (value, version), or None if the key is absent
+     */
+    fn read_versioned<Q>(&self, key: &Q) -> Option<(V, u64)>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let cache = self.read_state();
+        cache.live_with_key(key).map(|(key, entry)| {
+            cache.touch(key, entry);
+            (entry.value.clone(), entry.version)
+        })
+    }
+
+    /**
+     * Compare-and-swap on the version of an existing key
+     * 
+     * @param key The key to update
+     * @param expected_version Version obtained from read_versioned
+     * @param new The value to store
+     * @return true if the key still had `expected_version` and was updated,
+     *         false if it was modified or removed since, or `new` is invalid
+     */
+    fn write_if_version<Q>(&self, key: &Q, expected_version: u64, new: V) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let owned_key = key.to_owned();
+        if self.validate(&owned_key, &new).is_err() {
+            return false;
+        }
+        let mut cache = self.write_state();
+        
+        if cache.live(key).map(|entry| entry.version) != Some(expected_version) {
+            return false;
+        }
+        let version = cache.bump_version();
+        let entry = cache.map.get_mut(key).expect("live entry is in the map");
+        entry.value = new;
+        entry.version = version;
+        self.publish(&owned_key, &entry.value);
+        cache.dirty.insert(owned_key);
+        self.churn.inserts_overwrite.add(1);
+        self.stats.writes.add(1);
+        drop(cache);
+        
+        self.notify_changed();
+        true
+    }
+
+    /**
+     * Write operation - exclusive access required
+     * 
+     * Uses write(): only one thread can hold this lock
+     * Blocks all readers and other writers until complete
+     * 
+     * A write refused by a reject-on-full cache or by the validator is
+     * dropped silently, use write_checked to find out about it. A
+     * poisoned lock is recovered and written anyway.
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     */
+    fn write(&self, key: K, value: V) {
+        let _ = self.store(key, value, Expiry::default());
+    }
+
+    /**
+     * Write operation that reports refused writes
+     * 
+     * Unlike write, nothing is written to a poisoned cache.
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     * @return CapacityExceeded if the cache is full, `key` is new and no
+     *         entry may be evicted (reject-on-full mode, or all keys pinned);
+     *         Invalid if the validator refused the entry; Poisoned if a
+     *         thread panicked while holding the lock
+     */
+    fn write_checked(&self, key: K, value: V) -> Result<(), CacheError> {
+        self.validate(&key, &value)?;
+        let cache = self.cache.write().map_err(|_| CacheError::Poisoned)?;
+        self.store_locked(cache, key, value, Expiry::default())
+    }
+
+    /**
+     * Write a batch of entries under a single write lock
+     * 
+     * Much cheaper than calling write in a loop, and atomic with respect
+     * to readers: any read that runs concurrently sees either none or all
+     * of the batch. The entries are validated before the lock is taken;
+     * as with write, refused entries (invalid, or rejected by a full
+     * cache) are dropped silently and do not stop the rest of the batch.
+     * Later entries overwrite earlier ones with the same key.
+     * 
+     * @param entries The key/value pairs to store
+     */
+    fn write_many<I: IntoIterator<Item = (K, V)>>(&self, entries: I) {
+        let entries: Vec<(K, V)> = entries
+            .into_iter()
+            .filter(|(key, value)| self.validate(key, value).is_ok())
+            .collect();
+        
+        let mut cache = self.write_state();
+        for (key, value) in entries {
+            let _ = self.insert_locked(&mut cache, key, value, Expiry::default());
+        }
+        drop(cache);
+        
+        self.notify_changed();
+    }
+
+    /**
+     * Write an entry that expires `ttl` after this write
+     * 
+     * Refused writes are dropped as with write
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     * @param ttl Time-to-live
+     */
+    fn write_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        self.write_with_expiry(key, value, Some(ttl), None);
+    }
+
+    /**
+     * Write an entry that expires once it has not been read for `tti`
+     * 
+     * Every successful read restarts the idle clock, so an entry that is
+     * read more often than every `tti` never expires this way.
+     * Refused writes are dropped as with write
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     * @param tti Time-to-idle
+     */
+    fn write_with_tti(&self, key: K, value: V, tti: Duration) {
+        self.write_with_expiry(key, value, None, Some(tti));
+    }
+
+    /**
+     * Write an entry with any combination of time-to-live and time-to-idle
+     * 
+     * With both set, the entry expires when the first of them runs out.
+     * Overwriting a key replaces its expiration settings.
+     * Refused writes are dropped as with write
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     * @param ttl Time-to-live, None for no absolute limit
+     * @param tti Time-to-idle, None for no idle limit
+     */
+    fn write_with_expiry(&self, key: K, value: V, ttl: Option<Duration>, tti: Option<Duration>) {
+        let _ = self.store(key, value, Expiry { ttl, tti });
+    }
+
+    /**
+     * Shared implementation of the write family
+     */
+    fn store(&self, key: K, value: V, expiry: Expiry) -> Result<(), CacheError> {
+        // Validate first so a rejected entry never takes the write lock
+        self.validate(&key, &value)?;
+        
+        // Acquire write lock - exclusive access, blocks all other threads
+        let cache = self.write_state();
+        self.store_locked(cache, key, value, expiry)
+    }
+
+    /**
+     * Second half of store: insert, release the lock, wake waiters
+     */
+    fn store_locked(
+        &self,
+        mut cache: EscalatingWriteGuard<'_, CacheState<K, V>>,
+        key: K,
+        value: V,
+        expiry: Expiry,
+    ) -> Result<(), CacheError> {
+        self.insert_locked(&mut cache, key, value, expiry)?;
+        drop(cache);
+        
+        // Wake any thread blocked waiting for a key to appear
+        self.notify_changed();
+        Ok(())
+    }
+
+    /**
+     * Invalidate every entry written so far in O(1)
+     * 
+     * Entries remember the generation they were written in, and only
+     * entries of the current generation are live. Bumping it makes all
+     * earlier entries stale at once without touching them; like expired
+     * entries they read as absent and are removed lazily (or by
+     * purge_expired). Pinned entries are invalidated too.
+     * 
+     * Only the shared lock is taken: it excludes writers, so every write
+     * lands clearly before the bump (now stale) or after it (live).
+     */
+    fn bump_generation(&self) {
+        let cache = self.read_state();
+        cache.generation.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /**
+     * Remove every expired entry now instead of lazily
+     * 
+     * size() counts expired entries until they are removed, including
+     * those left stale by bump_generation
+     * 
+     * @return Number of entries removed
+     */
+    fn purge_expired(&self) -> usize {
+        let mut cache = self.write_state();
+        let expired: Vec<K> = cache
+            .map
+            .iter()
+            .filter(|(key, entry)| !cache.is_live(key, entry))
+            .map(|(key, _)| key.clone())
+            .collect();
+        for key in &expired {
+            cache.remove_entry(key);
+        }
+        expired.len()
+    }
+
+    /**
+     * Remove one key
+     * 
+     * @param key The key to invalidate
+     * @return The removed value, or None if the key was absent or expired
+     */
+    fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut cache = self.write_state();
+        if cache.live(key).is_none() {
+            cache.purge_if_expired(key);
+            return None;
+        }
+        cache.remove_entry(key).map(|(_, entry)| entry.value)
+    }
+
+    /**
+     * Remove all listed keys under a single write lock
+     * 
+     * Cheaper than removing the keys one at a time, and no reader sees
+     * only some of them gone. Absent and expired keys are skipped.
+     * 
+     * @param keys The keys to invalidate
+     * @return The removed (key, value) pairs, in the order the keys were given
+     */
+    fn remove_many<'a, Q, I>(&self, keys: I) -> Vec<(K, V)>
+    where
+        I: IntoIterator<Item = &'a Q>,
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized + 'a,
+    {
+        let mut cache = self.write_state();
+        let mut removed = Vec::new();
+        for key in keys {
+            if cache.live(key).is_none() {
+                // Drop an expired entry but do not report it
+                cache.purge_if_expired(key);
+                continue;
+            }
+            if let Some((key, entry)) = cache.remove_entry(key) {
+                removed.push((key, entry.value));
+            }
+        }
+        removed
+    }
+
+    /**
+     * Remove every entry and report how many there were
+     * 
+     * Counting and clearing happen under one write lock, so no write can
+     * slip in between and be lost from the count, and nothing is cloned.
+     * Like size(), the count includes expired entries not yet removed.
+     * Pins are kept: a pinned key written again is still protected.
+     * 
+     * @return Number of entries removed
+     */
+    fn clear_returning_count(&self) -> usize {
+        let mut cache = self.write_state();
+        let cache = &mut *cache;
+        let count = cache.map.len();
+        if let Some(policy) = &cache.policy {
+            policy.on_clear();
+        }
+        cache.map.clear();
+        cache.dirty.clear();
+        count
+    }
+
+    /**
+     * Remove every entry
+     * 
+     * Same as clear_returning_count, for callers that do not need the count
+     */
+    fn clear(&self) {
+        self.clear_returning_count();
+    }
+
+    /**
+     * Subscribe to updates of a single key
+     * 
+     * Every value subsequently stored under `key` is sent on the returned
+     * channel; writes to other keys never reach this watcher. Dropping
+     * the receiver unsubscribes: the sender is pruned on the next write.
+     * 
+     * @param key The key to watch
+     * @return Receiver of the key's new values
+     */
+    fn watch_key(&self, key: K) -> Receiver<V> {
+        let (tx, rx) = mpsc::channel();
+        let mut watchers = self.watchers.lock().unwrap_or_else(PoisonError::into_inner);
+        watchers.entry(key).or_default().push(tx);
+        rx
+    }
+
+    /**
+     * Make `key` immune to eviction until unpin is called
+     * 
+     * The pin belongs to the key rather than the entry: a key may be
+     * pinned before it is written, and stays pinned if it is removed
+     * and written again.
+     * 
+     * @param key The key to protect
+     */
+    fn pin<Q>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+    {
+        let mut cache = self.write_state();
+        cache.pinned.insert(key.to_owned());
+    }
+
+    /**
+     * Make `key` evictable again
+     * 
+     * @param key The key to release
+     */
+    fn unpin<Q>(&self, key: &Q)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut cache = self.write_state();
+        cache.pinned.remove(key);
+    }
+
+    /**
+     * Atomic read-modify-write of one key
+     * 
+     * `f` receives a clone of the current value (None if the key is absent
+     * or expired) and returns the new one: Some stores it, None removes
+     * the key. The write lock is held from the read to the store, so no
+     * other writer can slip in between and concurrent updates of a key
+     * are never lost. An existing entry keeps its expiry settings, as with
+     * write_merge; if `f` panics the stored value is left intact.
+     * 
+     * @param key The key to transform
+     * @param f Maps the current value to the new one
+     */
+    fn update<Q, F>(&self, key: &Q, f: F)
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ToOwned<Owned = K> + ?Sized,
+        F: FnOnce(Option<V>) -> Option<V>,
+    {
+        let mut cache = self.write_state();
+        
+        cache.purge_if_expired(key);
+        let current = cache.map.get(key).map(|entry| entry.value.clone());
+        match f(current) {
+            None => {
+                cache.remove_entry(key);
+            }
+            Some(value) => {
+                let version = cache.bump_version();
+                match cache.map.get_mut(key) {
+                    Some(entry) => {
+                        entry.value = value;
+                        entry.version = version;
+                        self.churn.inserts_overwrite.add(1);
+                        self.stats.writes.add(1);
+                        let owned_key = key.to_owned();
+                        self.publish(&owned_key, &entry.value);
+                        cache.dirty.insert(owned_key);
+                    }
+                    None => {
+                        let _ = self.insert_locked(&mut cache, key.to_owned(), value, Expiry::default());
+                    }
+                }
+            }
+        }
+        drop(cache);
+        
+        self.notify_changed();
+    }
+
+    /**
+     * Write that combines with an existing value instead of overwriting
+     * 
+     * If the key exists its value becomes merge(old, new), otherwise
+     * `value` is inserted as is (and dropped if a reject-on-full cache is full).
+     * An invalid `value` is dropped without calling `merge`. The whole read-combine-store runs under
+     * one write lock, so concurrent merges on a key are never lost.
+     * `merge` receives a clone of the old value, so if it panics the
+     * stored value is left intact.
+     * Useful for aggregation such as summing counters or appending lists.
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store or combine
+     * @param merge Combines (old, new) into the stored value
+     */
+    fn write_merge<F: FnOnce(V, V) -> V>(&self, key: K, value: V, merge: F) {
+        if self.validate(&key, &value).is_err() {
+            return;
+        }
+        let mut cache = self.write_state();
+        
+        cache.purge_if_expired(&key);
+        let version = cache.bump_version();
+        match cache.map.get_mut(&key) {
+            Some(entry) => {
+                entry.value = merge(entry.value.clone(), value);
+                entry.version = version;
+                self.churn.inserts_overwrite.add(1);
+                self.stats.writes.add(1);
+                self.publish(&key, &entry.value);
+                cache.dirty.insert(key);
+            }
+            None => {
+                let _ = self.insert_locked(&mut cache, key, value, Expiry::default());
+            }
+        }
+        drop(cache);
+        
+        self.notify_changed();
+    }
+
+    /**
+     * Block until `key` is present, then remove and return its value
+     * 
+     * Lets the cache act as a keyed mailbox. The check-and-remove happens
+     * under the write lock, so when several threads wait on the same key
+     * exactly one of them receives the value.
+     * 
+     * @param key The key to wait for
+     * @param timeout Maximum time to wait
+     * @return The taken value, or None if the timeout elapsed first or
+     *         the cache was interrupted
+     */
+    fn take_blocking<Q>(&self, key: &Q, timeout: Duration) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.take_blocking_checked(key, timeout).unwrap_or(None)
+    }
+
+    /**
+     * take_blocking that reports interruption
+     * 
+     * @param key The key to wait for
+     * @param timeout Maximum time to wait
+     * @return Ok(Some(value)) when taken, Ok(None) on timeout, or
+     *         Err(Interrupted) if an Interrupter was triggered
+     */
+    fn take_blocking_checked<Q>(&self, key: &Q, timeout: Duration) -> Result<Option<V>, CacheError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.wait_until_changed(timeout, || {
+            let mut cache = self.write_state();
+            if cache.live(key).is_some() {
+                cache.remove_entry(key).map(|(_, entry)| entry.value)
+            } else {
+                None
+            }
+        })
+    }
+
+    /**
+     * Block until `key` is present and return a copy of its value
+     * 
+     * Parks on the change Condvar instead of polling: every write wakes
+     * the waiters, which re-check for the key under the read lock. Unlike
+     * take_blocking the entry stays in the cache, so every waiter gets
+     * the value. The re-checks are not counted in stats().
+     * 
+     * @param key The key to wait for
+     * @param timeout Maximum time to wait
+     * @return The value, or None if the timeout elapsed first or the
+     *         cache was interrupted
+     */
+    fn wait_for<Q>(&self, key: &Q, timeout: Duration) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.wait_for_checked(key, timeout).unwrap_or(None)
+    }
+
+    /**
+     * wait_for that reports interruption
+     * 
+     * @param key The key to wait for
+     * @param timeout Maximum time to wait
+     * @return Ok(Some(value)) once present, Ok(None) on timeout, or
+     *         Err(Interrupted) if an Interrupter was triggered
+     */
+    fn wait_for_checked<Q>(&self, key: &Q, timeout: Duration) -> Result<Option<V>, CacheError>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.wait_until_changed(timeout, || self.read_uncounted(key))
+    }
+
+    /**
+     * Run `check` after every change until it returns Some, or `timeout`
+     * elapses, or the cache is interrupted
+     * 
+     * `check` must take the cache lock itself; it is called with
+     * change_lock held.
+     */
+    fn wait_until_changed<R, F>(&self, timeout: Duration, check: F) -> Result<Option<R>, CacheError>
+    where
+        F: Fn() -> Option<R>,
+    {
+        let deadline = Instant::now() + timeout;
+        
+        // Holding change_lock while checking means a write cannot slip in
+        // between the check and the wait: its notify needs the same mutex
+        let mut signal = self.change_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        loop {
+            if let Some(result) = check() {
+                return Ok(Some(result));
+            }
+            if self.interrupted.load(Ordering::SeqCst) {
+                return Err(CacheError::Interrupted);
+            }
+            
+            let now = Instant::now();
+            if now >= deadline {
+                return Ok(None);
+            }
+            signal = self
+                .changed
+                .wait_timeout(signal, deadline - now)
+                .unwrap_or_else(PoisonError::into_inner)
+                .0;
+        }
+    }
+
+    /**
+     * Parallel map-reduce over all cached values
+     * 
+     * The values are cloned under a single read lock, so the reduction
+     * sees one consistent snapshot and the lock is not held while mapping.
+     * The snapshot is split into `threads` chunks; each scoped thread maps
+     * its chunk and folds it starting from `identity`, then the partials
+     * are combined on the calling thread.
+     * 
+     * `combine` must be associative with `identity` as its neutral element,
+     * because the grouping of operations depends on how values are split
+     * into chunks. Map iteration order is unspecified, so `combine` should
+     * also be commutative for the result to be deterministic.
+     * 
+     * @param threads Number of worker threads (0 is treated as 1)
+     * @param identity Neutral element of `combine`
+     * @param map Turns a value into a partial result
+     * @param combine Joins two partial results
+     * @return The reduction of all values
+     */
+    fn par_reduce<A, M, C>(&self, threads: usize, identity: A, map: M, combine: C) -> A
+    where
+        V: Sync,
+        A: Send + Clone,
+        M: Fn(&V) -> A + Sync,
+        C: Fn(A, A) -> A + Sync,
+    {
+        let values: Vec<V> = {
+            let cache = self.read_state();
+            cache
+                .map
+                .iter()
+                .filter(|(key, entry)| cache.is_live(key, entry))
+                .map(|(_, entry)| entry.value.clone())
+                .collect()
+        };
+        if values.is_empty() {
+            return identity;
+        }
+
+        let chunk_size = values.len().div_ceil(threads.max(1));
+        let (map, combine) = (&map, &combine);
+        let partials: Vec<A> = thread::scope(|s| {
+            let handles: Vec<_> = values
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    let identity = identity.clone();
+                    s.spawn(move || {
+                        chunk.iter().fold(identity, |acc, value| combine(acc, map(value)))
+                    })
+                })
+                .collect();
+            handles.into_iter().map(|h| h.join().unwrap()).collect()
+        });
+
+        partials.into_iter().fold(identity, combine)
+    }
+
+    /**
+     * Owned point-in-time copy of the cache as a HashMap
+     * 
+     * Cloned under a single read lock; the returned map is independent of
+     * the cache, so callers can look things up in it at leisure without
+     * holding any lock. Expired entries are left out.
+     * 
+     * @return key -> value for every live entry
+     */
+    fn to_map(&self) -> HashMap<K, V> {
+        let cache = self.read_state();
+        cache
+            .map
+            .iter()
+            .filter(|(key, entry)| cache.is_live(key, entry))
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    /**
+     * Owned point-in-time copy of every live entry
+     * 
+     * Like to_map, the pairs are cloned under a single read lock that is
+     * released before the vector is returned, so iterating over it never
+     * holds up writers. Pairs are in no particular order; use iter_ordered
+     * for insertion order.
+     * 
+     * @return (key, value) for every live entry
+     */
+    fn snapshot(&self) -> Vec<(K, V)> {
+        let cache = self.read_state();
+        cache
+            .map
+            .iter()
+            .filter(|(key, entry)| cache.is_live(key, entry))
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    /**
+     * Keys of every live entry, without cloning the values
+     * 
+     * Taken under one read lock like snapshot, in no particular order
+     * 
+     * @return The live keys
+     */
+    fn keys(&self) -> Vec<K> {
+        let cache = self.read_state();
+        cache
+            .map
+            .iter()
+            .filter(|(key, entry)| cache.is_live(key, entry))
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /**
+     * Copy of all live entries in the order their keys were inserted
+     * 
+     * Overwriting a key keeps its place; removing it does not disturb the
+     * order of the others, and writing it again afterwards puts it last.
+     * The order is recovered by sorting on each entry's insertion
+     * version, so no separate list has to be kept in step with the map.
+     * 
+     * @return (key, value) pairs, oldest insertion first
+     */
+    fn iter_ordered(&self) -> Vec<(K, V)> {
+        let cache = self.read_state();
+        let mut entries: Vec<_> = cache
+            .map
+            .iter()
+            .filter(|(key, entry)| cache.is_live(key, entry))
+            .collect();
+        entries.sort_by_key(|(_, entry)| entry.inserted);
+        entries
+            .into_iter()
+            .map(|(key, entry)| (key.clone(), entry.value.clone()))
+            .collect()
+    }
+
+    /**
+     * Hand keys written since the last flush to a sharded backend
+     * 
+     * Drains the dirty set under the write lock and groups the current
+     * values of those keys by partition(key). The lock is released before
+     * `sink` is called, once per non-empty group in increasing partition
+     * order; pairs within a group are in no particular order.
+     * Dirty keys that were removed, evicted or have expired before the
+     * flush no longer have a value and are skipped.
+     * 
+     * @param partition Maps a key to its backend shard
+     * @param sink Receives (shard, pairs) for each group
+     */
+    fn flush_grouped<F, S>(&self, partition: F, sink: S)
+    where
+        F: Fn(&K) -> usize,
+        S: Fn(usize, Vec<(K, V)>),
+    {
+        let mut groups: BTreeMap<usize, Vec<(K, V)>> = BTreeMap::new();
+        {
+            let mut cache = self.write_state();
+            let dirty = std::mem::take(&mut cache.dirty);
+            for key in dirty {
+                if let Some(entry) = cache.live(&key) {
+                    let value = entry.value.clone();
+                    groups.entry(partition(&key)).or_default().push((key, value));
+                }
+            }
+        }
+        
+        for (group, pairs) in groups {
+            sink(group, pairs);
+        }
+    }
+
+    /**
+     * Insert/overwrite/eviction counts since the cache was created
+     * 
+     * @return The counters plus the evictions / inserts_new ratio
+     */
+    fn churn(&self) -> ChurnStats {
+        self.churn.snapshot()
+    }
+
+    /**
+     * Hit, miss and write counts since creation or the last reset_stats
+     * 
+     * Hits and misses are counted by every read-family call: read,
+     * try_read, try_read_checked, read_many and the calls built on read
+     * (read_or_else, read_async, the fast paths of get_or_load and
+     * get_or_insert_with). Writes count every
+     * entry stored or updated by the write family, write_if_version and
+     * write_merge; refused writes are not counted. The three values are
+     * read separately, so with concurrent traffic they are not one atomic
+     * snapshot.
+     * 
+     * @return The current counters
+     */
+    fn stats(&self) -> CacheStats {
+        self.stats.snapshot()
+    }
+
+    /**
+     * Zero the hit, miss and write counters
+     */
+    fn reset_stats(&self) {
+        self.stats.reset();
+    }
+
+    /**
+     * Proactive poison detection for monitoring threads
+     * 
+     * Probes the lock with try_read() instead of read(), so it never
+     * blocks behind a writer and never panics on a poisoned lock.
+     * 
+     * @return Healthy, Poisoned, or Contended if the lock is busy
+     */
+    fn health_check(&self) -> Health {
+        match self.cache.try_read() {
+            Ok(_) => Health::Healthy,
+            Err(TryLockError::Poisoned(_)) => Health::Poisoned,
+            Err(TryLockError::WouldBlock) => Health::Contended,
+        }
+    }
+
+    /**
+     * Whether a thread panicked while holding the cache lock
+     * 
+     * The cache keeps working after such a panic: every access recovers
+     * the lock instead of propagating the poison. The data may however be
+     * inconsistent (a write may have been applied halfway), so callers
+     * can use this to log the event, rebuild the cache, or fail over.
+     * 
+     * @return true once any thread has panicked under the lock
+     */
+    fn is_poisoned(&self) -> bool {
+        self.cache.is_poisoned()
+    }
+
+    /**
+     * Shared access to the state, recovering from poisoning
+     * 
+     * A poisoned lock still hands out its guard via PoisonError::into_inner,
+     * so one panicking writer does not make every later call panic too.
+     */
+    fn read_state(&self) -> EscalatingReadGuard<'_, CacheState<K, V>> {
+        self.cache.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /**
+     * Exclusive access to the state, recovering from poisoning
+     */
+    fn write_state(&self) -> EscalatingWriteGuard<'_, CacheState<K, V>> {
+        self.cache.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /**
+     * Run the configured validator, if any
+     */
+    fn validate(&self, key: &K, value: &V) -> Result<(), CacheError> {
+        match &self.validator {
+            Some(validator) => validator(key, value).map_err(CacheError::Invalid),
+            None => Ok(()),
+        }
+    }
+
+    /**
+     * Wake every thread blocked in take_blocking or wait_for so it
+     * re-checks the map
+     */
+    fn notify_changed(&self) {
+        let _signal = self.change_lock.lock().unwrap_or_else(PoisonError::into_inner);
+        self.changed.notify_all();
+    }
+
+    /**
+     * Insert or overwrite a key while the write lock is held
+     * 
+     * Overwriting an existing key never evicts or fails. Inserting a new
+     * key into a full cache evicts the policy's victim, or is refused in
+     * reject-on-full mode. If every key is pinned there is
+     * no victim and the insert is refused too: the cache never grows past
+     * its capacity. An expired entry is replaced as if the key were new.
+     */
+    fn insert_locked(&self, cache: &mut CacheState<K, V>, key: K, value: V, expiry: Expiry) -> Result<(), CacheError> {
+        cache.purge_if_expired(&key);
+        let version = cache.bump_version();
+        let generation = cache.current_generation();
+        if let Some(entry) = cache.map.get_mut(&key) {
+            entry.value = value;
+            entry.version = version;
+            entry.generation = generation;
+            entry.set_expiry(expiry, cache.epoch);
+            self.churn.inserts_overwrite.add(1);
+            self.stats.writes.add(1);
+            self.publish(&key, &entry.value);
+            cache.dirty.insert(key);
+            return Ok(());
+        }
+
+        match self.capacity {
+            CapacityMode::Unbounded => {}
+            CapacityMode::Evict(capacity) => {
+                if cache.map.len() >= capacity {
+                    let victim = cache.evict_victim().ok_or(CacheError::CapacityExceeded)?;
+                    cache.map.remove(&victim);
+                    self.churn.evictions.add(1);
+                }
+            }
+            CapacityMode::RejectOnFull(capacity) => {
+                if cache.map.len() >= capacity {
+                    return Err(CacheError::CapacityExceeded);
+                }
+            }
+        }
+
+        self.publish(&key, &value);
+        cache.dirty.insert(key.clone());
+        if let Some(policy) = &cache.policy {
+            policy.on_insert(&key);
+        }
+        let mut entry = Entry::new(value, version, generation);
+        entry.set_expiry(expiry, cache.epoch);
+        cache.map.insert(key, entry);
+        self.churn.inserts_new.add(1);
+        self.stats.writes.add(1);
+        Ok(())
+    }
+
+    /**
+     * Send a key's new value to its watchers, dropping disconnected ones
+     * 
+     * Called with the write lock held, so each watcher sees the updates
+     * to its key in the order they were applied
+     */
+    fn publish(&self, key: &K, value: &V) {
+        let mut watchers = self.watchers.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some(senders) = watchers.get_mut(key) {
+            senders.retain(|tx| tx.send(value.clone()).is_ok());
+            if senders.is_empty() {
+                watchers.remove(key);
+            }
+        }
+    }
+
+    /**
+     * Size query - read operation with shared access
+     * 
+     * Includes expired entries that have not been removed yet
+     * 
+     * @return Current number of entries in cache
+     */
+    fn size(&self) -> usize {
+        // Acquire read lock - can be called concurrently with other reads
+        let cache = self.read_state();
+        cache.map.len()
+    }
+}
+
+/**
+ * Marks a get_or_load as finished when dropped
+ * 
+ * Dropping also happens when the loader panics, so waiters are never
+ * left waiting on a load that will not complete
+ */
+struct InFlight<'a, K: Eq + Hash + Clone, V: Clone> {
+    cache: &'a ThreadSafeCache<K, V>,
+    key: K,
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> Drop for InFlight<'_, K, V> {
+    fn drop(&mut self) {
+        let mut loads = self.cache.loads.lock().unwrap_or_else(PoisonError::into_inner);
+        loads.remove(&self.key);
+        self.cache.load_done.notify_all();
+    }
+}
+
+impl<K, V> Interruptible for ThreadSafeCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync,
+    V: Clone + Send + Sync,
+{
+    /**
+     * Mark the cache interrupted and wake all blocked threads to notice
+     * 
+     * The flag is set before the mutexes are taken, and waiters check it
+     * while holding them, so no waiter can miss the wake-up.
+     */
+    fn interrupt(&self) {
+        self.interrupted.store(true, Ordering::SeqCst);
+        self.notify_changed();
+        let _loads = self.loads.lock().unwrap_or_else(PoisonError::into_inner);
+        self.load_done.notify_all();
+    }
+}
+
+impl<K: Eq + Hash + Clone, V: Clone> FromIterator<(K, V)> for ThreadSafeCache<K, V> {
+    /**
+     * Collect pairs into an unbounded cache, pre-sized from the size hint
+     */
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let iter = iter.into_iter();
+        let (lower, _) = iter.size_hint();
+        Self::from_iter_with_capacity(iter, lower)
+    }
+}
+
+/**
+ * Offloaded reads, for caches shared through an Arc
+ */
+impl<K, V> ThreadSafeCache<K, V>
+where
+    K: Eq + Hash + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /**
+     * read() on a background thread
+     * 
+     * The read runs on a pool of READ_POOL_THREADS threads that the cache
+     * starts on the first call, so the caller is not blocked while a
+     * writer holds the lock; it only waits once it asks the future for
+     * the value. Takes the Arc because the pool thread needs its own
+     * handle to the cache. Counts as a hit or a miss like read().
+     * 
+     * @param key The key to look up
+     * @return Future of a clone of the value, None inside if not found
+     */
+    fn read_async(self: &Arc<Self>, key: K) -> CacheFuture<Option<V>> {
+        let cache = Arc::clone(self);
+        self.read_pool
+            .get_or_init(|| ReadPool::new(READ_POOL_THREADS))
+            .spawn(move || cache.read(&key))
+    }
+}
+
+// Size of the pool a cache starts for read_async
+const READ_POOL_THREADS: usize = 2;
+
+// Work item run by a ReadPool thread
+type PoolJob = Box<dyn FnOnce() + Send + 'static>;
+
+/**
+ * Fixed set of threads running jobs from one mpsc queue
+ * 
+ * Backs read_async, so an offloaded read does not cost a fresh OS
+ * thread. The workers share the receiver behind a Mutex and release it
+ * before running a job. Dropping the pool closes the queue; the workers
+ * finish what is queued and exit. They are not joined: the last Arc to
+ * the cache, and with it the pool, may be dropped by a job running on
+ * one of them.
+ */
+struct ReadPool {
+    // Behind a Mutex so the pool, and the cache holding it, stay Sync
+    sender: Mutex<Sender<PoolJob>>,
+}
+
+impl ReadPool {
+    fn new(threads: usize) -> Self {
+        let (sender, receiver) = mpsc::channel::<PoolJob>();
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..threads {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || loop {
+                let job = receiver.lock().unwrap_or_else(PoisonError::into_inner).recv();
+                match job {
+                    // A panicking job must not take its worker down with it
+                    Ok(job) => {
+                        let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                    }
+                    Err(_) => break,
+                }
+            });
+        }
+        ReadPool { sender: Mutex::new(sender) }
+    }
+
+    /**
+     * Queue `job` and return the future its result is delivered to
+     */
+    fn spawn<T, F>(&self, job: F) -> CacheFuture<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (promise, receiver) = mpsc::channel();
+        let job: PoolJob = Box::new(move || {
+            // The future may have been dropped, then the result is too
+            let _ = promise.send(job());
+        });
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .send(job)
+            .expect("pool threads run until the pool is dropped");
+        CacheFuture { receiver }
+    }
+}
+
+/**
+ * Result of an operation running on another thread
+ * 
+ * The receiving end of a one-shot channel whose sender, the promise, is
+ * moved into the job and fulfilled exactly once. A job that panics drops
+ * the promise unfulfilled, and the future then yields None.
+ */
+struct CacheFuture<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> CacheFuture<T> {
+    /**
+     * Block until the job has finished
+     * 
+     * @return The job's result, or None if it panicked
+     */
+    fn wait(self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+
+    /**
+     * Take the result if the job has already finished, without blocking
+     * 
+     * @return What wait would return, or the future back while the job
+     *         is still queued or running
+     */
+    fn try_wait(self) -> Result<Option<T>, Self> {
+        match self.receiver.try_recv() {
+            Ok(value) => Ok(Some(value)),
+            Err(TryRecvError::Disconnected) => Ok(None),
+            Err(TryRecvError::Empty) => Err(self),
+        }
+    }
+}
+
+/**
+ * File persistence, for key and value types with a text form
+ */
+impl<K, V> ThreadSafeCache<K, V>
+where
+    K: Eq + Hash + Clone + fmt::Display + FromStr,
+    V: Clone + fmt::Display + FromStr,
+{
+    /**
+     * Write every live entry to `path`, one `key=value` line each
+     * 
+     * The entries are copied with snapshot(), so the lock is released
+     * before any file I/O. Backslash, `=`, newline and carriage return in
+     * keys and values are escaped (see escape_field), so any text
+     * round-trips through load_from. Lines are in no particular order;
+     * expiry settings, versions and pins are not saved.
+     * 
+     * @param path File to create or overwrite
+     * @return Any error from creating or writing the file
+     */
+    fn save_to(&self, path: &Path) -> io::Result<()> {
+        let entries = self.snapshot();
+        let mut out = BufWriter::new(File::create(path)?);
+        for (key, value) in entries {
+            writeln!(out, "{}={}", escape_field(&key.to_string()), escape_field(&value.to_string()))?;
+        }
+        out.flush()
+    }
+
+    /**
+     * Add the entries saved in `path` with save_to
+     * 
+     * The whole file is parsed before anything is stored, then inserted
+     * with write_many under one write lock: a file that fails to parse
+     * leaves the cache untouched, and readers never see it half loaded.
+     * Existing entries with the same keys are overwritten; the usual
+     * capacity and validation rules apply.
+     * 
+     * @param path File written by save_to
+     * @return InvalidData if a line is malformed or does not parse as
+     *         K/V, or any error from reading the file
+     */
+    fn load_from(&self, path: &Path) -> io::Result<()> {
+        let mut entries = Vec::new();
+        for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let invalid = |what: &str| io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", number + 1, what));
+            
+            let (key, value) = split_escaped_line(&line).ok_or_else(|| invalid("expected key=value"))?;
+            let key = unescape_field(key).ok_or_else(|| invalid("bad escape in key"))?;
+            let value = unescape_field(value).ok_or_else(|| invalid("bad escape in value"))?;
+            let key = key.parse().map_err(|_| invalid("key does not parse"))?;
+            let value = value.parse().map_err(|_| invalid("value does not parse"))?;
+            entries.push((key, value));
+        }
+        self.write_many(entries);
+        Ok(())
+    }
+}
+
+/**
+ * Escape a key or value for one line of a saved cache file
+ * 
+ * `\` becomes `\\`, `=` becomes `\=`, newline `\n` and carriage return `\r`,
+ * so the first unescaped `=` always separates key from value
+ */
+fn escape_field(field: &str) -> String {
+    let mut escaped = String::with_capacity(field.len());
+    for c in field.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '=' => escaped.push_str("\\="),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/**
+ * Reverse of escape_field; None on an unknown or dangling escape
+ */
+fn unescape_field(field: &str) -> Option<String> {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut chars = field.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+        unescaped.push(match chars.next()? {
+            '\\' => '\\',
+            '=' => '=',
+            'n' => '\n',
+            'r' => '\r',
+            _ => return None,
+        });
+    }
+    Some(unescaped)
+}
+
+/**
+ * Split a saved line at its first unescaped `=`, still escaped
+ */
+fn split_escaped_line(line: &str) -> Option<(&str, &str)> {
+    let mut escaped = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '=' => return Some((&line[..i], &line[i + 1..])),
+            _ => {}
+        }
+    }
+    None
+}
+
+/**
+ * Cooperative cancellation flag shared by worker threads
+ * 
+ * Clones share the same flag; once cancelled it stays cancelled.
+ */
+#[derive(Clone, Default)]
+struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    fn new() -> Self {
+        CancellationToken::default()
+    }
+
+    /**
+     * Token backed by an existing flag, e.g. one the caller already shares
+     * 
+     * Setting the flag and calling cancel() are then the same thing
+     */
+    fn from_flag(flag: Arc<AtomicBool>) -> Self {
+        CancellationToken { cancelled: flag }
+    }
+
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+}
+
+// Longest single sleep in interruptible_sleep, bounding cancellation latency
+const SLEEP_SLICE: Duration = Duration::from_millis(10);
+
+/**
+ * thread::sleep that gives up early when `token` is cancelled
+ * 
+ * Sleeps in slices of at most SLEEP_SLICE and checks the token between
+ * them, so a cancelled worker wakes within about one slice instead of
+ * waiting out the full duration.
+ * 
+ * @param duration How long to sleep
+ * @param token Cancellation flag checked between slices
+ * @return true if the full duration was slept, false if cancelled
+ */
+fn interruptible_sleep(duration: Duration, token: &CancellationToken) -> bool {
+    let deadline = Instant::now() + duration;
+    loop {
+        if token.is_cancelled() {
+            return false;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return true;
+        }
+        thread::sleep(SLEEP_SLICE.min(deadline - now));
+    }
+}
+
+// "Null pointer" of the arena-backed list
+const NIL: usize = usize::MAX;
+
+/**
+ * Node of an LruList, linked to its neighbours by arena index
+ */
+struct LruNode<K> {
+    // None while the slot sits on the free list
+    key: Option<K>,
+    prev: usize,
+    next: usize,
+}
+
+/**
+ * Doubly-linked recency list stored in a Vec arena
+ * 
+ * Nodes refer to each other by index instead of by pointer, so there is
+ * no Rc<RefCell<..>> or unsafe code to satisfy the borrow checker. Freed
+ * slots are recycled through a free list. The head is the most recently
+ * used key, the tail the least recently used one; every operation is O(1).
+ * Clock reuses it as a plain ring, inserting in the middle instead.
+ */
+struct LruList<K> {
+    nodes: Vec<LruNode<K>>,
+    free: Vec<usize>,
+    head: usize,
+    tail: usize,
+}
+
+impl<K> LruList<K> {
+    fn new() -> Self {
+        LruList {
+            nodes: Vec::new(),
+            free: Vec::new(),
+            head: NIL,
+            tail: NIL,
+        }
+    }
+
+    /**
+     * Insert `key` as most recently used
+     * 
+     * @return The node index, to be kept for move_to_front/remove
+     */
+    fn push_front(&mut self, key: K) -> usize {
+        let index = self.alloc(key);
+        self.link_front(index);
+        index
+    }
+
+    /**
+     * Insert `key` just before the node at `next`, or at the back if
+     * `next` is NIL
+     * 
+     * @return The node index, to be kept for remove
+     */
+    fn insert_before(&mut self, next: usize, key: K) -> usize {
+        let index = self.alloc(key);
+        let prev = if next == NIL { self.tail } else { self.nodes[next].prev };
+        self.nodes[index].prev = prev;
+        self.nodes[index].next = next;
+        if prev == NIL {
+            self.head = index;
+        } else {
+            self.nodes[prev].next = index;
+        }
+        if next == NIL {
+            self.tail = index;
+        } else {
+            self.nodes[next].prev = index;
+        }
+        index
+    }
+
+    /**
+     * The node after `index`, NIL at the back
+     */
+    fn next_of(&self, index: usize) -> usize {
+        self.nodes[index].next
+    }
+
+    /**
+     * The node before `index`, NIL at the front
+     */
+    fn prev_of(&self, index: usize) -> usize {
+        self.nodes[index].prev
+    }
+
+    /**
+     * The key stored at `index`
+     */
+    fn key_at(&self, index: usize) -> &K {
+        self.nodes[index].key.as_ref().expect("LRU node already freed")
+    }
+
+    /**
+     * Mark the key at `index` as most recently used
+     */
+    fn move_to_front(&mut self, index: usize) {
+        if self.head != index {
+            self.unlink(index);
+            self.link_front(index);
+        }
+    }
+
+    /**
+     * Remove the key at `index` and recycle its slot
+     */
+    fn remove(&mut self, index: usize) -> K {
+        self.unlink(index);
+        self.free.push(index);
+        self.nodes[index].key.take().expect("LRU node already freed")
+    }
+
+    /**
+     * Remove and return the least recently used key
+     */
+    fn pop_back(&mut self) -> Option<K> {
+        if self.tail == NIL {
+            None
+        } else {
+            Some(self.remove(self.tail))
+        }
+    }
+
+    fn alloc(&mut self, key: K) -> usize {
+        let node = LruNode { key: Some(key), prev: NIL, next: NIL };
+        match self.free.pop() {
+            Some(index) => {
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn link_front(&mut self, index: usize) {
+        self.nodes[index].prev = NIL;
+        self.nodes[index].next = self.head;
+        if self.head != NIL {
+            self.nodes[self.head].prev = index;
+        }
+        self.head = index;
+        if self.tail == NIL {
+            self.tail = index;
+        }
+    }
+
+    fn unlink(&mut self, index: usize) {
+        let (prev, next) = (self.nodes[index].prev, self.nodes[index].next);
+        if prev == NIL {
+            self.head = next;
+        } else {
+            self.nodes[prev].next = next;
+        }
+        if next == NIL {
+            self.tail = prev;
+        } else {
+            self.nodes[next].prev = prev;
+        }
+    }
+}
+
+/**
+ * State of a ConcurrentLru: key -> (list node, value) plus recency order
+ */
+struct LruState {
+    map: HashMap<String, (usize, String)>,
+    order: LruList<String>,
+}
+
+/**
+ * Concurrent LRU cache with O(1) read and write
+ * 
+ * Unlike ThreadSafeCache it uses a single Mutex rather than an RwLock:
+ * every read reorders the recency list, so reads need exclusive access
+ * anyway. Finding the victim is O(1) because it is always the list tail.
+ */
+struct ConcurrentLru {
+    state: Mutex<LruState>,
+    capacity: usize,
+}
+
+impl ConcurrentLru {
+    /**
+     * Create an LRU cache holding at most `capacity` entries
+     * 
+     * @param capacity Maximum number of entries, must be greater than zero
+     */
+    fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "cache capacity must be greater than zero");
+        ConcurrentLru {
+            state: Mutex::new(LruState {
+                map: HashMap::with_capacity(capacity),
+                order: LruList::new(),
+            }),
+            capacity,
+        }
+    }
+
+    /**
+     * Look up a key and mark it most recently used
+     * 
+     * @param key The key to look up
+     * @return The value if found
+     */
+    fn read(&self, key: &str) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        let (index, value) = state.map.get(key)?;
+        state.order.move_to_front(*index);
+        Some(value.clone())
+    }
+
+    /**
+     * Insert or overwrite a key, evicting the least recently used if full
+     * 
+     * @param key The key to insert/update
+     * @param value The value to store
+     * @return The evicted key, if any
+     */
+    fn write(&self, key: String, value: String) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+        
+        if let Some((index, old)) = state.map.get_mut(&key) {
+            *old = value;
+            state.order.move_to_front(*index);
+            return None;
+        }
+        
+        let evicted = if state.map.len() >= self.capacity {
+            let victim = state.order.pop_back().expect("full cache has a tail");
+            state.map.remove(&victim);
+            Some(victim)
+        } else {
+            None
+        };
+        let index = state.order.push_front(key.clone());
+        state.map.insert(key, (index, value));
+        evicted
+    }
+
+    /**
+     * @return Current number of entries
+     */
+    fn size(&self) -> usize {
+        self.state.lock().unwrap().map.len()
+    }
+}
+
+/**
+ * Strategy that decides which key a full cache evicts
+ * 
+ * The cache tells the policy about every key it stores and drops, and
+ * asks it for a victim when a new key needs room. on_insert, on_remove,
+ * on_clear and evict_victim are called with the cache's write lock held, one at a
+ * time; on_access is called by readers holding only the shared lock, so
+ * it may run on many threads at once. The methods take &self and
+ * policies use their own interior locking.
+ */
+trait EvictionPolicy<K>: Send + Sync {
+    // A cached key was read
+    fn on_access(&self, key: &K);
+    // A new key was stored
+    fn on_insert(&self, key: &K);
+    // A key left the cache other than by eviction
+    fn on_remove(&self, key: &K);
+    // Every key left the cache at once
+    fn on_clear(&self);
+    // Choose, and forget, the key to evict, passing over the keys for
+    // which `pinned` is true without changing their state; None if every
+    // tracked key is pinned
+    fn evict_victim(&self, pinned: &dyn Fn(&K) -> bool) -> Option<K>;
+}
+
+/**
+ * Least recently used: evicts the key whose last access is oldest
+ * 
+ * Exact recency, at the cost of a Mutex taken on every cache hit to
+ * reorder the list (see ConcurrentLru); all operations are O(1).
+ */
+struct Lru<K> {
+    state: Mutex<LruPolicyState<K>>,
+}
+
+struct LruPolicyState<K> {
+    order: LruList<K>,
+    // key -> node index in `order`
+    nodes: HashMap<K, usize>,
+}
+
+impl<K> Lru<K> {
+    fn new() -> Self {
+        Lru {
+            state: Mutex::new(LruPolicyState {
+                order: LruList::new(),
+                nodes: HashMap::new(),
+            }),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send> EvictionPolicy<K> for Lru<K> {
+    fn on_access(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(&index) = state.nodes.get(key) {
+            state.order.move_to_front(index);
+        }
+    }
+
+    fn on_insert(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        let index = state.order.push_front(key.clone());
+        state.nodes.insert(key.clone(), index);
+    }
+
+    fn on_remove(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(index) = state.nodes.remove(key) {
+            state.order.remove(index);
+        }
+    }
+
+    fn on_clear(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.order = LruList::new();
+        state.nodes.clear();
+    }
+
+    fn evict_victim(&self, pinned: &dyn Fn(&K) -> bool) -> Option<K> {
+        let mut state = self.state.lock().unwrap();
+        // Walk from the least recently used end past any pinned keys
+        let mut index = state.order.tail;
+        while index != NIL && pinned(state.order.key_at(index)) {
+            index = state.order.prev_of(index);
+        }
+        if index == NIL {
+            return None;
+        }
+        let victim = state.order.remove(index);
+        state.nodes.remove(&victim);
+        Some(victim)
+    }
+}
+
+/**
+ * Least frequently used: evicts the key accessed the fewest times
+ * 
+ * Ties go to the key least recently used. Counting is O(1) under a
+ * Mutex; choosing the victim scans all keys, O(n), which keeps the
+ * bookkeeping to a single map.
+ */
+struct Lfu<K> {
+    state: Mutex<LfuState<K>>,
+}
+
+struct LfuState<K> {
+    // key -> (access count, tick of the last access)
+    counts: HashMap<K, (u64, u64)>,
+    tick: u64,
+}
+
+impl<K> Lfu<K> {
+    fn new() -> Self {
+        Lfu {
+            state: Mutex::new(LfuState {
+                counts: HashMap::new(),
+                tick: 0,
+            }),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send> EvictionPolicy<K> for Lfu<K> {
+    fn on_access(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        if let Some((count, last)) = state.counts.get_mut(key) {
+            *count += 1;
+            *last = tick;
+        }
+    }
+
+    fn on_insert(&self, key: &K) {
+        let mut state = self.state.lock().unwrap();
+        state.tick += 1;
+        let tick = state.tick;
+        // Storing the key counts as its first use
+        state.counts.insert(key.clone(), (1, tick));
+    }
+
+    fn on_remove(&self, key: &K) {
+        self.state.lock().unwrap().counts.remove(key);
+    }
+
+    fn on_clear(&self) {
+        self.state.lock().unwrap().counts.clear();
+    }
+
+    fn evict_victim(&self, pinned: &dyn Fn(&K) -> bool) -> Option<K> {
+        let mut state = self.state.lock().unwrap();
+        let victim = state
+            .counts
+            .iter()
+            .filter(|(key, _)| !pinned(key))
+            .min_by_key(|(_, &usage)| usage)
+            .map(|(key, _)| key.clone())?;
+        state.counts.remove(&victim);
+        Some(victim)
+    }
+}
+
+/**
+ * CLOCK (second-chance): a cheap approximation of LRU
+ * 
+ * Keys sit in a circular list swept by a hand. A read only sets the
+ * key's reference bit, an AtomicBool reached through a shared lock, so
+ * concurrent readers never serialize on the policy. When a victim is
+ * needed the sweep clears set bits and evicts the first key whose bit
+ * was already clear; two full sweeps are enough to find one. A new key
+ * goes just behind the hand, where the last victim was, so it gets a
+ * full rotation before it is considered. The ring is an LruList found
+ * through a key -> node map, so inserting and removing a key is O(1).
+ */
+struct Clock<K> {
+    state: RwLock<ClockState<K>>,
+}
+
+struct ClockState<K> {
+    // Swept from head to tail, then around again
+    ring: LruList<K>,
+    // Node the hand points at; NIL once it has passed the tail
+    hand: usize,
+    // key -> (node in `ring`, reference bit)
+    slots: HashMap<K, (usize, AtomicBool)>,
+}
+
+impl<K> ClockState<K> {
+    fn new() -> Self {
+        ClockState {
+            ring: LruList::new(),
+            hand: NIL,
+            slots: HashMap::new(),
+        }
+    }
+}
+
+impl<K> Clock<K> {
+    fn new() -> Self {
+        Clock {
+            state: RwLock::new(ClockState::new()),
+        }
+    }
+}
+
+impl<K: Eq + Hash + Clone + Send + Sync> EvictionPolicy<K> for Clock<K> {
+    fn on_access(&self, key: &K) {
+        let state = self.state.read().unwrap();
+        if let Some((_, bit)) = state.slots.get(key) {
+            bit.store(true, Ordering::Relaxed);
+        }
+    }
+
+    fn on_insert(&self, key: &K) {
+        let mut state = self.state.write().unwrap();
+        let hand = state.hand;
+        let node = state.ring.insert_before(hand, key.clone());
+        state.slots.insert(key.clone(), (node, AtomicBool::new(false)));
+    }
+
+    fn on_remove(&self, key: &K) {
+        let mut state = self.state.write().unwrap();
+        if let Some((node, _)) = state.slots.remove(key) {
+            if state.hand == node {
+                state.hand = state.ring.next_of(node);
+            }
+            state.ring.remove(node);
+        }
+    }
+
+    fn on_clear(&self) {
+        *self.state.write().unwrap() = ClockState::new();
+    }
+
+    fn evict_victim(&self, pinned: &dyn Fn(&K) -> bool) -> Option<K> {
+        let mut state = self.state.write().unwrap();
+        let state = &mut *state;
+        for _ in 0..2 * state.slots.len() {
+            if state.hand == NIL {
+                state.hand = state.ring.head;
+            }
+            let node = state.hand;
+            // The hand moves on whether or not this key is the victim
+            state.hand = state.ring.next_of(node);
+            let key = state.ring.key_at(node);
+            // A pinned key keeps its reference bit for when it is unpinned
+            if pinned(key) || state.slots[key].1.swap(false, Ordering::Relaxed) {
+                continue;
+            }
+            let victim = state.ring.remove(node);
+            state.slots.remove(&victim);
+            return Some(victim);
+        }
+        None
+    }
+}
+
+/**
+ * Generic cache demo
+ * 
+ * The same cache type holding u64 -> Vec<u8> blobs with LRU eviction,
+ * and String -> struct records read concurrently through a &str
+ */
+fn demo_generic_types() {
+    let blobs: ThreadSafeCache<u64, Vec<u8>> = ThreadSafeCache::with_policy(2, Lru::new());
+    blobs.write(1, vec![0xde, 0xad]);
+    blobs.write(2, vec![0xbe, 0xef]);
+    blobs.read(&1);
+    blobs.write(3, vec![0x00]);
+    println!("blobs: 1 = {:?}, 2 = {:?}, 3 = {:?}, size {}", blobs.read(&1), blobs.read(&2), blobs.read(&3), blobs.size());
+
+    #[derive(Debug, Clone, PartialEq)]
+    struct Profile {
+        name: String,
+        age: u32,
+    }
+
+    let profiles: Arc<ThreadSafeCache<String, Profile>> = Arc::new(ThreadSafeCache::new());
+    profiles.write("ada".to_string(), Profile { name: "Ada".to_string(), age: 36 });
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let profiles = Arc::clone(&profiles);
+            thread::spawn(move || profiles.read("ada"))
+        })
+        .collect();
+    for reader in readers {
+        println!("profile: {:?}", reader.join().unwrap());
+    }
+    println!("missing profile: {:?}", profiles.read("bob"));
+}
+
+/**
+ * Cancellable sleep demo
+ * 
+ * A worker starts a 10s sleep and is cancelled after 30ms; it wakes
+ * within one slice and reports that it did not sleep the full duration
+ */
+fn demo_interruptible_sleep() {
+    let token = CancellationToken::new();
+    let start = Instant::now();
+    let worker = {
+        let token = token.clone();
+        thread::spawn(move || interruptible_sleep(Duration::from_secs(10), &token))
+    };
+
+    thread::sleep(Duration::from_millis(30));
+    token.cancel();
+    let slept_fully = worker.join().unwrap();
+    println!("slept fully: {}, returned after {:?}", slept_fully, start.elapsed());
+}
+
+/**
+ * CLOCK eviction demo
+ * 
+ * "hot" is read before the cache overflows, so its reference bit buys it
+ * a second chance; "cold" was never read and is the entry evicted
+ */
+fn demo_clock_eviction() {
+    let cache = ThreadSafeCache::with_clock_eviction(3);
+    cache.write("hot".to_string(), "1".to_string());
+    cache.write("cold".to_string(), "2".to_string());
+    cache.write("warm".to_string(), "3".to_string());
+
+    cache.read("hot");
+    cache.read("warm");
+    cache.write("new".to_string(), "4".to_string());
+
+    println!("size after overflow: {}", cache.size());
+    println!("hot: {:?}", cache.read("hot"));
+    println!("cold: {:?}", cache.read("cold"));
+}
+
+/**
+ * Demo configuration runs
+ * 
+ * Five readers much faster than the writer are sure to miss keys; with
+ * two writers done before the readers start, nothing is missed
+ */
+fn demo_config() {
+    let racing = DemoConfig {
+        readers: 5,
+        writer_delay: Duration::from_millis(40),
+        reader_delay: Duration::from_millis(5),
+        read_iters: 6,
+        verbose: false,
+        ..DemoConfig::default()
+    };
+    let misses = run(racing, Arc::new(ThreadSafeCache::new()));
+    println!("fast readers: {} Not found (at least one: {})", misses, misses > 0);
+
+    let writers_first = DemoConfig {
+        writers: 2,
+        readers: 5,
+        writer_delay: Duration::ZERO,
+        reader_delay: Duration::from_millis(1),
+        reader_start_delay: Duration::from_millis(50),
+        verbose: false,
+        ..DemoConfig::default()
+    };
+    println!("writers first: {} Not found", run(writers_first, Arc::new(ThreadSafeCache::new())));
+}
+
+/**
+ * Cooperative shutdown demo
+ * 
+ * The reader-writer demo would run for about half a second; setting the
+ * stop flag after 120ms makes every worker exit and be joined promptly
+ */
+fn demo_cooperative_shutdown() {
+    let stop = Arc::new(AtomicBool::new(false));
+    let demo = {
+        let stop = Arc::clone(&stop);
+        thread::spawn(move || run(DemoConfig { stop, ..DemoConfig::default() }, Arc::new(ThreadSafeCache::new())))
+    };
+
+    thread::sleep(Duration::from_millis(120));
+    let requested = Instant::now();
+    stop.store(true, Ordering::SeqCst);
+    demo.join().unwrap();
+    let waited = requested.elapsed();
+    println!("all workers joined {:?} after stop (within 50ms: {})", waited, waited < Duration::from_millis(50));
+}
+
+/**
+ * Non-blocking read demo
+ * 
+ * While another thread holds the write lock in a slow merge, try_read
+ * reports Locked at once instead of waiting; afterwards it finds the
+ * key, or reports it missing
+ */
+fn demo_try_read() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    cache.write("k".to_string(), "v".to_string());
+
+    let slow = Arc::clone(&cache);
+    let holder = thread::spawn(move || {
+        slow.write_merge("k".to_string(), "v".to_string(), |old, _| {
+            thread::sleep(Duration::from_millis(100));
+            old
+        });
+    });
+    thread::sleep(Duration::from_millis(30));
+    let start = Instant::now();
+    let during = cache.try_read("k");
+    println!("while writing: {:?} (returned in under 10ms: {})", during, start.elapsed() < Duration::from_millis(10));
+    holder.join().unwrap();
+
+    println!("after the write: {:?}", cache.try_read("k"));
+    println!("absent key: {:?}", cache.try_read("nope"));
+}
+
+/**
+ * Double-checked read-through demo
+ * 
+ * Eight threads miss on the same key at once; only the first one to get
+ * the write lock runs the loader, the others find its value on re-check
+ */
+fn demo_get_or_insert_with() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    let calls = Arc::new(AtomicUsize::new(0));
+
+    let handles: Vec<_> = (0..8)
+        .map(|_| {
+            let cache = Arc::clone(&cache);
+            let calls = Arc::clone(&calls);
+            thread::spawn(move || {
+                cache.get_or_insert_with("config", || {
+                    calls.fetch_add(1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    "loaded".to_string()
+                })
+            })
+        })
+        .collect();
+
+    let results: Vec<String> = handles.into_iter().map(|handle| handle.join().unwrap()).collect();
+    println!("loader calls: {} (expected 1)", calls.load(Ordering::SeqCst));
+    println!("all threads got the loaded value: {}", results.iter().all(|value| value == "loaded"));
+}
+
+/**
+ * Statistics demo
+ * 
+ * A known sequence of reads and writes, with the expected counts printed
+ * alongside; the refused write is not counted
+ */
+fn demo_stats() {
+    let cache = ThreadSafeCache::with_reject_on_full(2);
+    cache.write("a".to_string(), "1".to_string());
+    cache.write("b".to_string(), "2".to_string());
+    cache.write("a".to_string(), "3".to_string());
+    cache.write("c".to_string(), "refused".to_string());
+
+    for key in ["a", "b", "c", "a", "x"].iter() {
+        cache.read(*key);
+    }
+    println!("{:?} (expected 3 hits, 2 misses, 3 writes)", cache.stats());
+
+    cache.reset_stats();
+    cache.read("b");
+    println!("after reset and one read: {:?}", cache.stats());
+}
+
+/**
+ * Capacity bound demo
+ * 
+ * Five keys go into a cache of three; reading "a" makes it recent, so
+ * "b" and then "c" are the least recently used entries evicted
+ */
+fn demo_with_capacity() {
+    let cache = ThreadSafeCache::with_capacity(3);
+    cache.write("a".to_string(), "1".to_string());
+    cache.write("b".to_string(), "2".to_string());
+    cache.write("c".to_string(), "3".to_string());
+    cache.read("a");
+
+    for (key, value) in [("d", "4"), ("e", "5")].iter() {
+        cache.write(key.to_string(), value.to_string());
+        println!("after writing {}: size {}", key, cache.size());
+    }
+    println!("evicted: {:?}", cache.missing_keys(["a", "b", "c", "d", "e"].iter().copied()));
+    println!("a: {:?}", cache.read("a"));
+}
+
+/**
+ * Read-modify-write demo
+ * 
+ * Eight threads each append 25 characters to the same key through
+ * update; none of the 200 appends is lost. A closure returning None
+ * then removes the key.
+ */
+fn demo_update() {
+    let cache = Arc::new(ThreadSafeCache::new());
+
+    let handles: Vec<_> = (0..8)
+        .map(|i| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                let mark = char::from(b'a' + i as u8);
+                for _ in 0..25 {
+                    cache.update("log", |old: Option<String>| {
+                        let mut log = old.unwrap_or_default();
+                        log.push(mark);
+                        Some(log)
+                    });
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+
+    let length = cache.read("log").map_or(0, |log| log.len());
+    println!("log length: {} (expected 200)", length);
+
+    cache.update("log", |_| None);
+    println!("after returning None: {:?}", cache.read("log"));
+}
+
+/**
+ * Merge-on-write demo
+ * 
+ * Several threads add their contributions to one counter key; summing in
+ * the merge closure means no contribution is lost to an overwrite
+ */
+fn demo_write_merge() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    let sum = |old: String, new: String| {
+        (old.parse::<u64>().unwrap() + new.parse::<u64>().unwrap()).to_string()
+    };
+
+    let workers: Vec<_> = (1..=4)
+        .map(|i| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                for _ in 0..10 {
+                    cache.write_merge("total".to_string(), i.to_string(), sum);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    // 10 * (1 + 2 + 3 + 4)
+    println!("total: {:?} (expected 100)", cache.read("total"));
+}
+
+/**
+ * Parallel reduce demo
+ * 
+ * Sums 1..=100 stored as strings on four threads and checks the result
+ * against a plain sequential fold
+ */
+fn demo_par_reduce() {
+    let cache = ThreadSafeCache::new();
+    for i in 1..=100 {
+        cache.write(format!("n{}", i), i.to_string());
+    }
+
+    let parse = |v: &String| v.parse::<u64>().unwrap();
+    let parallel = cache.par_reduce(4, 0, parse, |a, b| a + b);
+    let sequential: u64 = (1..=100).sum();
+    println!("parallel sum: {}, sequential sum: {}", parallel, sequential);
+}
+
+/**
+ * Offloaded read demo
+ * 
+ * A slow merge holds the write lock while the main thread asks for the
+ * key with read_async: the call returns at once and the future is still
+ * pending. Once the merge is done the future yields the merged value.
+ */
+fn demo_read_async() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    cache.write("k".to_string(), "old".to_string());
+
+    let (started, wait_started) = mpsc::channel();
+    let writer = {
+        let cache = Arc::clone(&cache);
+        thread::spawn(move || {
+            cache.write_merge("k".to_string(), "new".to_string(), |_, new| {
+                started.send(()).unwrap();
+                thread::sleep(Duration::from_millis(100));
+                new
+            });
+        })
+    };
+    wait_started.recv().unwrap();
+
+    // The outer Option of wait() is None only if the read panicked
+    match cache.read_async("k".to_string()).try_wait() {
+        Ok(value) => println!("ready while the writer holds the lock: {:?}", value),
+        Err(future) => {
+            println!("pending while the writer holds the lock: true");
+            println!("k: {:?} (expected Some(\"new\"))", future.wait().flatten());
+        }
+    }
+    writer.join().unwrap();
+    println!("missing: {:?}", cache.read_async("missing".to_string()).wait().flatten());
+}
+
+/**
+ * Health check demo
+ * 
+ * Probes a cache while a slow merge holds the write lock, then poisons
+ * it by panicking inside a merge and probes again. The _checked calls
+ * then return Err(Poisoned), while plain reads and writes still work.
+ */
+fn demo_health_check() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    cache.write("k".to_string(), "v".to_string());
+    println!("idle: {:?}", cache.health_check());
+
+    let slow = Arc::clone(&cache);
+    let holder = thread::spawn(move || {
+        slow.write_merge("k".to_string(), "v".to_string(), |old, _| {
+            thread::sleep(Duration::from_millis(100));
+            old
+        });
+    });
+    thread::sleep(Duration::from_millis(30));
+    println!("while writing: {:?}", cache.health_check());
+    holder.join().unwrap();
+
+    // The merge closure runs under the write lock, so panicking in it poisons the lock
+    println!("(the panic message below is expected)");
+    let poisoner = Arc::clone(&cache);
+    let result = thread::spawn(move || {
+        poisoner.write_merge("k".to_string(), "v".to_string(), |_, _| panic!("merge failed"));
+    })
+    .join();
+    println!("writer panicked: {}", result.is_err());
+    println!("after panic: {:?}, is_poisoned: {}", cache.health_check(), cache.is_poisoned());
+
+    // The checked calls report the poison instead of using the lock
+    println!("try_read_checked: {:?}", cache.try_read_checked("k"));
+    println!("write_checked: {:?}", cache.write_checked("k2".to_string(), "v2".to_string()));
+    if let Err(e) = cache.try_read_checked("k") {
+        println!("as an error: {}", e);
+    }
+
+    // Reads and writes recover the poisoned lock and keep working
+    cache.write("k2".to_string(), "v2".to_string());
+    println!("still usable: k = {:?}, k2 = {:?}, size {}", cache.read("k"), cache.read("k2"), cache.size());
+}
+
+/**
+ * Reject-on-full demo
+ * 
+ * A full cache refuses a new key but still accepts an overwrite
+ */
+fn demo_reject_on_full() {
+    let cache = ThreadSafeCache::with_reject_on_full(2);
+    cache.write("a".to_string(), "1".to_string());
+    cache.write("b".to_string(), "2".to_string());
+
+    match cache.write_checked("c".to_string(), "3".to_string()) {
+        Ok(()) => println!("new key accepted"),
+        Err(e) => println!("new key rejected: {}", e),
+    }
+    println!("overwrite: {:?}", cache.write_checked("a".to_string(), "10".to_string()));
+    println!("size: {}, a: {:?}", cache.size(), cache.read("a"));
+}
+
+/**
+ * Sharded counter demo
+ * 
+ * Eight threads hammer one counter; the shard sum equals the total
+ * number of increments performed
+ */
+fn demo_sharded_counter() {
+    let counter = Arc::new(ShardedCounter::new());
+    let workers: Vec<_> = (0..8)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..10_000 {
+                    counter.add(1);
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    println!("sum: {} (expected 80000)", counter.sum());
+}
+
+/**
+ * Validator demo
+ * 
+ * Empty values are refused with the validator's message and leave the
+ * cache unchanged; a non-empty value is stored
+ */
+fn demo_validator() {
+    let cache = ThreadSafeCache::<String, String>::new().with_validator(|key, value| {
+        if value.is_empty() {
+            Err(format!("empty value for key '{}'", key))
+        } else {
+            Ok(())
+        }
+    });
+
+    println!("empty value: {:?}", cache.write_checked("name".to_string(), String::new()));
+    println!("size after rejection: {}", cache.size());
+    println!("valid value: {:?}", cache.write_checked("name".to_string(), "Ada".to_string()));
+    println!("name: {:?}", cache.read("name"));
+}
+
+/**
+ * Expiration demo
+ * 
+ * A session with a 100ms time-to-idle survives half a second of reads
+ * every 50ms, then expires once the reads stop. An entry with both a
+ * 150ms TTL and a long TTI expires on the TTL even though it is read.
+ */
+fn demo_expiration() {
+    let cache = ThreadSafeCache::new();
+    cache.write_with_ttl("banner".to_string(), "hello".to_string(), Duration::from_millis(30));
+    println!("banner right after write: {:?}", cache.read("banner"));
+    cache.write_with_tti("session".to_string(), "alice".to_string(), Duration::from_millis(100));
+    cache.write_with_expiry(
+        "token".to_string(),
+        "abc".to_string(),
+        Some(Duration::from_millis(150)),
+        Some(Duration::from_secs(10)),
+    );
+
+    for i in 1..=10 {
+        thread::sleep(Duration::from_millis(50));
+        let (session, token) = (cache.read("session"), cache.read("token"));
+        if i % 2 == 0 {
+            println!("after {}ms: session = {:?}, token = {:?}", i * 50, session, token);
+        }
+    }
+
+    thread::sleep(Duration::from_millis(150));
+    println!("after reads stopped: session = {:?}, banner = {:?}", cache.read("session"), cache.read("banner"));
+    println!("size {}, purged {} expired entries", cache.size(), cache.purge_expired());
+    println!("size after purge: {}", cache.size());
+}
+
+/**
+ * Fair lock stress demo
+ * 
+ * Eight readers loop on the cache, each holding the shared lock for 5ms
+ * at a time, so the lock is practically never free. With a fair lock a
+ * single write still gets in within a few read slices.
+ */
+fn demo_fairness() {
+    let cache = Arc::new(ThreadSafeCache::with_fairness());
+    cache.write("config".to_string(), "initial".to_string());
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..8)
+        .map(|i| {
+            let cache = Arc::clone(&cache);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(i));
+                let mut reads = 0u64;
+                while !stop.load(Ordering::Relaxed) {
+                    let _guard = cache.cache.read().unwrap();
+                    thread::sleep(Duration::from_millis(5));
+                    reads += 1;
+                }
+                reads
+            })
+        })
+        .collect();
+
+    thread::sleep(Duration::from_millis(30));
+    let start = Instant::now();
+    cache.write("config".to_string(), "updated".to_string());
+    let waited = start.elapsed();
+
+    stop.store(true, Ordering::Relaxed);
+    let reads: u64 = readers.into_iter().map(|reader| reader.join().unwrap()).sum();
+    println!("write completed under read load (within 500ms: {})", waited < Duration::from_millis(500));
+    println!("readers kept going: {}, config = {:?}", reads > 0, cache.read("config"));
+}
+
+/**
+ * Writer escalation demo
+ * 
+ * Four readers hold the lock in overlapping 20ms slices so that at any
+ * moment at least one of them is inside. The writer is escalated after
+ * 50ms, new readers are held back and the write goes through shortly after.
+ */
+fn demo_writer_escalation() {
+    let cache = Arc::new(ThreadSafeCache::new().with_writer_escalation(Duration::from_millis(50)));
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let readers: Vec<_> = (0..4)
+        .map(|i| {
+            let cache = Arc::clone(&cache);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(i * 5));
+                while !stop.load(Ordering::Relaxed) {
+                    // Simulate a long read holding the shared lock
+                    let _guard = cache.cache.read().unwrap();
+                    thread::sleep(Duration::from_millis(20));
+                }
+            })
+        })
+        .collect();
+
+    thread::sleep(Duration::from_millis(30));
+    let start = Instant::now();
+    cache.write("config".to_string(), "updated".to_string());
+    println!("write completed after {:?} under constant read load", start.elapsed());
+
+    stop.store(true, Ordering::Relaxed);
+    for reader in readers {
+        reader.join().unwrap();
+    }
+}
+
+/**
+ * Eviction policy demo
+ * 
+ * The same accesses are replayed on three caches of capacity 3: "a" is
+ * read three times, then "b" and "c" once each, and a fourth key is
+ * written. LRU evicts "a" (least recent), LFU evicts "b" (fewest reads,
+ * older than "c") and CLOCK evicts "a" (every bit was set, so the sweep
+ * comes back round to where it started). Up to the overflow the three
+ * caches return exactly the same reads.
+ */
+fn demo_eviction_policies() {
+    let caches = vec![
+        ("LRU", ThreadSafeCache::with_policy(3, Lru::new())),
+        ("LFU", ThreadSafeCache::with_policy(3, Lfu::new())),
+        ("CLOCK", ThreadSafeCache::with_policy(3, Clock::new())),
+    ];
+
+    let mut reads = Vec::new();
+    for (name, cache) in &caches {
+        for key in ["a", "b", "c"].iter() {
+            cache.write(key.to_string(), key.to_uppercase());
+        }
+        let seen: Vec<Option<String>> = ["a", "a", "a", "b", "c", "x"].iter().map(|key| cache.read(*key)).collect();
+        reads.push(seen);
+
+        cache.write("d".to_string(), "D".to_string());
+        println!("{:>5}: evicted {:?}", name, cache.missing_keys(["a", "b", "c"].iter().copied()));
+    }
+    println!("same reads before overflow: {}", reads.windows(2).all(|pair| pair[0] == pair[1]));
+}
+
+/**
+ * Pinning demo
+ * 
+ * "a" is the entry CLOCK would evict next, but it is pinned so "b" goes
+ * instead. Once every entry is pinned, a new key is refused until one
+ * is unpinned. With LRU, an unpinned key is evicted as if it had never
+ * been pinned.
+ */
+fn demo_pinning() {
+    let cache = ThreadSafeCache::with_clock_eviction(2);
+    cache.write("a".to_string(), "1".to_string());
+    cache.write("b".to_string(), "2".to_string());
+    cache.pin("a");
+
+    cache.write("c".to_string(), "3".to_string());
+    println!("a: {:?}, b: {:?}, c: {:?}", cache.read("a"), cache.read("b"), cache.read("c"));
+
+    cache.pin("c");
+    println!("all pinned, new key: {:?}", cache.write_checked("d".to_string(), "4".to_string()));
+
+    cache.unpin("a");
+    println!("after unpin, new key: {:?}", cache.write_checked("d".to_string(), "4".to_string()));
+    println!("a: {:?}, d: {:?}", cache.read("a"), cache.read("d"));
+
+    // Passing over a pinned key leaves its recency alone: "x" was never
+    // read, so once unpinned it is still the least recently used
+    let lru = ThreadSafeCache::with_policy(3, Lru::new());
+    for key in ["x", "y", "z"].iter() {
+        lru.write(key.to_string(), key.to_uppercase());
+    }
+    lru.pin("x");
+    lru.write("w".to_string(), "W".to_string());
+    lru.unpin("x");
+    lru.write("v".to_string(), "V".to_string());
+    println!("LRU after unpin: x: {:?}, y: {:?}, z: {:?}", lru.read("x"), lru.read("y"), lru.read("z"));
+}
+
+/**
+ * Uncached fallback demo
+ * 
+ * The fallback value is returned but the key stays absent
+ */
+fn demo_read_or_else() {
+    let cache = ThreadSafeCache::new();
+    cache.write("present".to_string(), "cached".to_string());
+
+    println!("present: {}", cache.read_or_else("present", || "fallback".to_string()));
+    println!("missing: {}", cache.read_or_else("missing", || "fallback".to_string()));
+    println!("missing afterwards: {:?} (size {})", cache.read("missing"), cache.size());
+}
+
+/**
+ * Coalesced load demo
+ * 
+ * One thread runs a slow loader; two threads missing on the same key
+ * wait for it but give up after the 50ms coalesce timeout. The loader
+ * still finishes and caches the value, having run only once.
+ */
+fn demo_coalesced_load() {
+    let cache = Arc::new(ThreadSafeCache::new().with_coalesce_timeout(Duration::from_millis(50)));
+    let loader_calls = Arc::new(AtomicUsize::new(0));
+
+    let callers: Vec<_> = (0..3)
+        .map(|i| {
+            let cache = Arc::clone(&cache);
+            let loader_calls = Arc::clone(&loader_calls);
+            thread::spawn(move || {
+                // Let caller 0 start the load first
+                thread::sleep(Duration::from_millis(i * 10));
+                cache.get_or_load("report", || {
+                    loader_calls.fetch_add(1, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(200));
+                    Some("expensive result".to_string())
+                })
+            })
+        })
+        .collect();
+
+    for (i, caller) in callers.into_iter().enumerate() {
+        println!("caller {}: {:?}", i, caller.join().unwrap());
+    }
+    println!("loader calls: {}, cached: {:?}", loader_calls.load(Ordering::SeqCst), cache.read("report"));
+}
+
+/**
+ * Bloom filter demo
+ * 
+ * The source holds 100 keys, all inserted into the filter. Looking up
+ * those keys plus 1000 that the source does not have, the loader only
+ * runs for the 100 real keys and the few false positives.
+ */
+fn demo_bloom_filter() {
+    let source: HashMap<String, String> = (0..100).map(|i| (format!("user{}", i), format!("profile{}", i))).collect();
+    let filter = Arc::new(BloomFilter::new(source.len(), 0.01));
+    for key in source.keys() {
+        filter.insert(key);
+    }
+
+    let cache = ThreadSafeCache::new().with_bloom_filter(filter);
+    let loader_calls = AtomicUsize::new(0);
+    let mut found = 0;
+    for i in 0..1100 {
+        let key = format!("user{}", i);
+        let loaded = cache.get_or_load(&key, || {
+            loader_calls.fetch_add(1, Ordering::Relaxed);
+            source.get(&key).cloned()
+        });
+        if let Ok(Some(_)) = loaded {
+            found += 1;
+        }
+    }
+    let calls = loader_calls.load(Ordering::Relaxed);
+    println!("lookups: 1100, found: {}, loader calls: {}", found, calls);
+    println!("skipped by the filter: {}, false positives: {}", 1100 - calls, calls - found);
+}
+
+/**
+ * Existence and prefix count demo
+ * 
+ * key0..key4 plus two other entries; "key" and "key1" match a subset
+ */
+fn demo_contains_and_count() {
+    let cache = ThreadSafeCache::new();
+    for i in 0..5 {
+        cache.write(format!("key{}", i), format!("value{}", i));
+    }
+    cache.write("other".to_string(), "x".to_string());
+    cache.write("key10".to_string(), "y".to_string());
+
+    println!("contains key3: {}, contains key7: {}", cache.contains_key("key3"), cache.contains_key("key7"));
+    println!("prefix \"key\": {}, \"key1\": {}, \"zzz\": {}", cache.count_prefix("key"), cache.count_prefix("key1"), cache.count_prefix("zzz"));
+}
+
+/**
+ * Missing-keys demo
+ * 
+ * Only the desired keys that were never written are reported
+ */
+fn demo_missing_keys() {
+    let cache = ThreadSafeCache::new();
+    cache.write("key0".to_string(), "value0".to_string());
+    cache.write("key2".to_string(), "value2".to_string());
+
+    let desired = ["key0", "key1", "key2", "key3"];
+    println!("missing: {:?}", cache.missing_keys(desired.iter().copied()));
+}
+
+/**
+ * Key watcher demo
+ * 
+ * Writes to "a" and "b" are interleaved, the watcher of "a" only
+ * receives the values written to "a"
+ */
+fn demo_watch_key() {
+    let cache = ThreadSafeCache::new();
+    let updates = cache.watch_key("a".to_string());
+
+    cache.write("a".to_string(), "a1".to_string());
+    cache.write("b".to_string(), "b1".to_string());
+    cache.write("a".to_string(), "a2".to_string());
+
+    let received: Vec<String> = updates.try_iter().collect();
+    println!("watcher of a received: {:?}", received);
+}
+
+/**
+ * Grouped flush demo
+ * 
+ * Six keys are written and one removed; the flush splits the remaining
+ * five into even/odd shards. A second flush finds nothing dirty.
+ */
+fn demo_flush_grouped() {
+    let cache = ThreadSafeCache::new();
+    for i in 0..6 {
+        cache.write(format!("key{}", i), format!("value{}", i));
+    }
+    cache.remove("key3");
+
+    let shard_of = |key: &String| key[3..].parse::<usize>().unwrap() % 2;
+    let print_group = |shard: usize, mut pairs: Vec<(String, String)>| {
+        pairs.sort();
+        println!("shard {}: {:?}", shard, pairs);
+    };
+    cache.flush_grouped(shard_of, print_group);
+
+    println!("second flush:");
+    cache.flush_grouped(shard_of, print_group);
+}
+
+/**
+ * Snapshot demo
+ * 
+ * Changes made to the cache after to_map() do not show up in the copy;
+ * snapshot() and keys() list exactly the entries written
+ */
+fn demo_to_map() {
+    let cache = ThreadSafeCache::new();
+    cache.write("a".to_string(), "1".to_string());
+    cache.write("b".to_string(), "2".to_string());
+
+    let copy = cache.to_map();
+    cache.write("a".to_string(), "changed".to_string());
+    cache.write("c".to_string(), "3".to_string());
+
+    println!("copy: a = {:?}, c = {:?}, {} entries", copy.get("a"), copy.get("c"), copy.len());
+    println!("cache: a = {:?}, {} entries", cache.read("a"), cache.size());
+
+    let mut pairs = cache.snapshot();
+    pairs.sort();
+    let mut keys = cache.keys();
+    keys.sort();
+    println!("snapshot: {:?}", pairs);
+    println!("keys: {:?}", keys);
+}
+
+/**
+ * Generation bump demo
+ * 
+ * Entries written before the bump read as None, the one written
+ * after it is visible; the stale ones linger until purged
+ */
+fn demo_bump_generation() {
+    let cache = ThreadSafeCache::new();
+    cache.write("old1".to_string(), "a".to_string());
+    cache.write("old2".to_string(), "b".to_string());
+    cache.pin("old2");
+
+    cache.bump_generation();
+    cache.write("new".to_string(), "c".to_string());
+
+    println!("old1: {:?}, old2 (pinned): {:?}, new: {:?}", cache.read("old1"), cache.read("old2"), cache.read("new"));
+    println!("size {}, purged {}, size {}", cache.size(), cache.purge_expired(), cache.size());
+}
+
+/**
+ * Removal demo
+ * 
+ * Removing a key twice returns None the second time; in the batch only
+ * the present keys are returned and the absent one is skipped. clear()
+ * then empties what is left.
+ */
+fn demo_remove_many() {
+    let cache = ThreadSafeCache::new();
+    for i in 0..4 {
+        cache.write(format!("session{}", i), format!("user{}", i));
+    }
+
+    println!("remove session0: {:?}", cache.remove("session0"));
+    println!("remove it again: {:?}", cache.remove("session0"));
+
+    let removed = cache.remove_many(["session1", "session9", "session3"].iter().copied());
+    println!("removed: {:?}", removed);
+    println!("missing now: {:?}", cache.missing_keys(["session0", "session1", "session2", "session3"].iter().copied()));
+
+    cache.clear();
+    println!("size after clear: {}", cache.size());
+}
+
+/**
+ * Clear-and-count demo
+ * 
+ * The returned count matches the size before, the cache is empty after
+ */
+fn demo_clear_returning_count() {
+    let cache = ThreadSafeCache::with_clock_eviction(8);
+    for i in 0..5 {
+        cache.write(format!("key{}", i), format!("value{}", i));
+    }
+
+    let before = cache.size();
+    let removed = cache.clear_returning_count();
+    println!("size before: {}, removed: {}, size after: {}", before, removed, cache.size());
+
+    // The eviction policy forgot the keys too, so the cache fills up normally again
+    cache.write("fresh".to_string(), "value".to_string());
+    println!("after refill: fresh = {:?}, size {}", cache.read("fresh"), cache.size());
+}
+
+/**
+ * Insertion order demo
+ * 
+ * Overwriting "b" keeps its place, taking "c" leaves the others in order
+ * and re-inserting it moves it to the end
+ */
+fn demo_iter_ordered() {
+    let cache = ThreadSafeCache::new();
+    for key in ["d", "b", "e", "c", "a"].iter() {
+        cache.write(key.to_string(), key.to_uppercase());
+    }
+    cache.write("b".to_string(), "B2".to_string());
+    cache.remove("c");
+    println!("in order: {:?}", cache.iter_ordered());
+
+    cache.write("c".to_string(), "C2".to_string());
+    println!("after re-insert: {:?}", cache.iter_ordered());
+}
+
+/**
+ * Optimistic update demo
+ * 
+ * The first compare-and-swap succeeds; the second one uses a version that
+ * a "concurrent" writer has since invalidated, so it is refused
+ */
+fn demo_versioned_update() {
+    let cache = ThreadSafeCache::new();
+    cache.write("config".to_string(), "v1".to_string());
+
+    let (value, version) = cache.read_versioned("config").unwrap();
+    let updated = cache.write_if_version("config", version, format!("{}+edit", value));
+    println!("update with fresh version {}: {}", version, updated);
+
+    let (_, stale) = cache.read_versioned("config").unwrap();
+    cache.write("config".to_string(), "other writer".to_string());
+    let updated = cache.write_if_version("config", stale, "lost update".to_string());
+    println!("update with stale version {}: {}", stale, updated);
+    println!("config: {:?}", cache.read("config"));
+}
+
+/**
+ * Persistence demo
+ * 
+ * Entries whose keys and values contain `=`, backslashes and newlines
+ * are saved, loaded into a fresh cache, and compared
+ */
+fn demo_save_and_load() {
+    let cache = ThreadSafeCache::new();
+    cache.write("plain".to_string(), "value".to_string());
+    cache.write("a=b".to_string(), "x=1\\y=2".to_string());
+    cache.write("multi\nline".to_string(), "C:\\temp\\=\r\n".to_string());
+    cache.write("empty".to_string(), String::new());
+
+    let path = std::env::temp_dir().join(format!("reader_writer_cache_{}.txt", std::process::id()));
+    let restored = ThreadSafeCache::<String, String>::new();
+    let result = cache.save_to(&path).and_then(|_| restored.load_from(&path));
+    let _ = std::fs::remove_file(&path);
+
+    let mut saved = cache.snapshot();
+    saved.sort();
+    let mut loaded = restored.snapshot();
+    loaded.sort();
+    println!("save and load: {:?}, {} entries restored", result, loaded.len());
+    println!("snapshots equal: {}", saved == loaded);
+}
+
+/**
+ * Batch write demo
+ * 
+ * A reader keeps looking up all keys of a batch while the batch is
+ * written; since write_many and read_many each take the lock once, the
+ * reader sees all of the keys or none of them, never a partial batch
+ */
+fn demo_write_many() {
+    let cache = Arc::new(ThreadSafeCache::new());
+    let keys: Vec<String> = (0..50).map(|i| format!("batch{}", i)).collect();
+    let done = Arc::new(AtomicBool::new(false));
+
+    let reader = {
+        let cache = Arc::clone(&cache);
+        let keys = keys.clone();
+        let done = Arc::clone(&done);
+        thread::spawn(move || {
+            let keys: Vec<&str> = keys.iter().map(String::as_str).collect();
+            let (mut empty, mut complete, mut partial) = (0, 0, 0);
+            loop {
+                // Checked before the lookup, so the final pass sees the batch
+                let finished = done.load(Ordering::SeqCst);
+                let found = cache.read_many(&keys).iter().filter(|value| value.is_some()).count();
+                match found {
+                    0 => empty += 1,
+                    n if n == keys.len() => complete += 1,
+                    _ => partial += 1,
+                }
+                if finished {
+                    return (empty, complete, partial);
+                }
+            }
+        })
+    };
+
+    thread::sleep(Duration::from_millis(5));
+    cache.write_many(keys.iter().map(|key| (key.clone(), "loaded".to_string())));
+    done.store(true, Ordering::SeqCst);
+
+    let (empty, complete, partial) = reader.join().unwrap();
+    println!("reader saw the batch absent: {}, complete: {}, partial: {} times", empty > 0, complete > 0, partial);
+    println!("size: {}", cache.size());
+}
+
+/**
+ * Bulk-load demo
+ * 
+ * Seeds 10k entries into a map pre-sized for 10k: its allocated capacity
+ * stays at the initial allocation, i.e. it never had to grow
+ */
+fn demo_bulk_load() {
+    let pairs = (0..10_000).map(|i| (format!("key{}", i), format!("value{}", i)));
+    let cache = ThreadSafeCache::from_iter_with_capacity(pairs, 10_000);
+
+    let allocated = cache.cache.read().unwrap().map.capacity();
+    let presized = HashMap::<String, Entry<String>>::with_capacity(10_000).capacity();
+    println!("entries: {}, allocated: {}, initial allocation: {}", cache.size(), allocated, presized);
+
+    let collected: ThreadSafeCache<String, String> = vec![("a".to_string(), "1".to_string())].into_iter().collect();
+    println!("collected via FromIterator: a = {:?}", collected.read("a"));
+}
+
+/**
+ * Deterministic pseudo-random numbers for the LRU demo and the lock
+ * benchmark (xorshift)
+ */
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+/**
+ * LRU correctness check and benchmark
+ * 
+ * Replays a random read/write sequence against ConcurrentLru and against
+ * a trivially correct (but O(n)) reference model, comparing every eviction
+ * decision, then times the O(1) cache under 4 threads
+ */
+fn demo_concurrent_lru() {
+    const CAPACITY: usize = 16;
+    let lru = ConcurrentLru::new(CAPACITY);
+    // Reference model: keys ordered from least to most recently used
+    let mut model: Vec<String> = Vec::new();
+    let mut mismatches = 0;
+    let mut rng = 0x2545_f491_4f6c_dd1d;
+
+    for _ in 0..20_000 {
+        let key = format!("k{}", next_random(&mut rng) % 40);
+        let position = model.iter().position(|k| *k == key);
+        if next_random(&mut rng) % 10 < 7 {
+            let hit = lru.read(&key).is_some();
+            if let Some(position) = position {
+                let k = model.remove(position);
+                model.push(k);
+            }
+            mismatches += usize::from(hit != position.is_some());
+        } else {
+            let evicted = lru.write(key.clone(), "v".to_string());
+            let expected = match position {
+                Some(position) => {
+                    model.remove(position);
+                    None
+                }
+                None if model.len() >= CAPACITY => Some(model.remove(0)),
+                None => None,
+            };
+            model.push(key);
+            mismatches += usize::from(evicted != expected);
+        }
+    }
+    println!("size: {}, mismatches against reference LRU: {}", lru.size(), mismatches);
+
+    let lru = Arc::new(ConcurrentLru::new(1024));
+    let ops_per_thread = 200_000;
+    let start = Instant::now();
+    let workers: Vec<_> = (0..4u64)
+        .map(|t| {
+            let lru = Arc::clone(&lru);
+            thread::spawn(move || {
+                let mut rng = t + 1;
+                for _ in 0..ops_per_thread {
+                    let key = format!("k{}", next_random(&mut rng) % 2048);
+                    if next_random(&mut rng) % 10 < 8 {
+                        lru.read(&key);
+                    } else {
+                        lru.write(key, "v".to_string());
+                    }
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    let elapsed = start.elapsed();
+    println!(
+        "benchmark: {} ops on 4 threads in {:?} ({:.0} ops/s)",
+        4 * ops_per_thread,
+        elapsed,
+        (4 * ops_per_thread) as f64 / elapsed.as_secs_f64()
+    );
+}
+
+/**
+ * Churn statistics demo
+ * 
+ * Two new keys fill a 2-entry cache, one overwrite, then two more new
+ * keys that each evict: 4 new, 1 overwrite, 2 evictions, ratio 0.5
+ */
+fn demo_churn() {
+    let cache = ThreadSafeCache::with_clock_eviction(2);
+    for key in ["a", "b", "a", "c", "d"] {
+        cache.write(key.to_string(), "v".to_string());
+    }
+    println!("{:?}", cache.churn());
+}
+
+/**
+ * Keyed mailbox demo
+ * 
+ * Two threads block on the same key; a single write arrives and exactly
+ * one of them takes it, the other times out
+ */
+fn demo_take_blocking() {
+    let cache = Arc::new(ThreadSafeCache::new());
+
+    let waiters: Vec<_> = (0..2)
+        .map(|_| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || cache.take_blocking("mailbox", Duration::from_millis(300)))
+        })
+        .collect();
+
+    thread::sleep(Duration::from_millis(50));
+    cache.write("mailbox".to_string(), "letter".to_string());
+
+    let received: Vec<_> = waiters
+        .into_iter()
+        .filter_map(|w| w.join().unwrap())
+        .collect();
+    println!("received by {} waiter(s): {:?}", received.len(), received);
+    println!("left in cache: {}", cache.size());
+}
+
+/**
+ * Blocking wait demo
+ * 
+ * Two readers wait for a key the writer only produces after 50ms; both
+ * wake with the value as soon as it is written and it stays cached. A
+ * wait for a key that never comes times out with None.
+ */
+fn demo_wait_for() {
+    let cache = Arc::new(ThreadSafeCache::new());
+
+    let readers: Vec<_> = (0..2)
+        .map(|_| {
+            let cache = Arc::clone(&cache);
+            thread::spawn(move || {
+                let start = Instant::now();
+                let value = cache.wait_for("report", Duration::from_secs(5));
+                (value, start.elapsed())
+            })
+        })
+        .collect();
+
+    thread::sleep(Duration::from_millis(50));
+    cache.write("report".to_string(), "ready".to_string());
+
+    for reader in readers {
+        let (value, waited) = reader.join().unwrap();
+        println!("woke with {:?} (well before the timeout: {})", value, waited < Duration::from_secs(1));
+    }
+    println!("still cached: {:?}", cache.read("report"));
+    println!("missing key: {:?}", cache.wait_for("never", Duration::from_millis(30)));
+}
+
+/**
+ * Interrupter demo
+ * 
+ * One thread blocks in take_blocking_checked on the first cache, another
+ * waits on a slow in-flight load of the second. A single trigger wakes
+ * both long before their own waits would have ended.
+ */
+fn demo_interrupter() {
+    let interrupter = Interrupter::new();
+    let mailbox = Arc::new(ThreadSafeCache::<String, String>::new());
+    let reports = Arc::new(ThreadSafeCache::<String, String>::new());
+    interrupter.register(&mailbox);
+    interrupter.register(&reports);
+
+    let start = Instant::now();
+    let taker = {
+        let mailbox = Arc::clone(&mailbox);
+        thread::spawn(move || mailbox.take_blocking_checked("letter", Duration::from_secs(10)))
+    };
+    let loader = {
+        let reports = Arc::clone(&reports);
+        thread::spawn(move || {
+            reports.get_or_load("report", || {
+                thread::sleep(Duration::from_millis(500));
+                Some("expensive result".to_string())
+            })
+        })
+    };
+    let waiter = {
+        let reports = Arc::clone(&reports);
+        thread::spawn(move || {
+            // Arrive while the load above is in flight
+            thread::sleep(Duration::from_millis(20));
+            reports.get_or_load("report", || Some("never used".to_string()))
+        })
+    };
+
+    thread::sleep(Duration::from_millis(50));
+    interrupter.trigger();
+    println!("taker: {:?}", taker.join().unwrap());
+    println!("load waiter: {:?}", waiter.join().unwrap());
+    println!("both woke after {:?}", start.elapsed());
+
+    // The running loader was not blocked, so it completes normally
+    println!("loader: {:?}", loader.join().unwrap());
+}
+
+/**
+ * Minimal map interface shared by the benchmarked lock types
+ */
+trait BenchMap: Sync {
+    // Look up every key in `keys` under one lock acquisition, return the sum
+    fn read_batch(&self, keys: &[u64]) -> u64;
+    fn write(&self, key: u64, value: u64);
+}
+
+impl BenchMap for RwLock<HashMap<u64, u64>> {
+    fn read_batch(&self, keys: &[u64]) -> u64 {
+        let map = self.read().unwrap();
+        keys.iter().filter_map(|key| map.get(key)).sum()
+    }
+
+    fn write(&self, key: u64, value: u64) {
+        self.write().unwrap().insert(key, value);
+    }
+}
+
+impl BenchMap for Mutex<HashMap<u64, u64>> {
+    fn read_batch(&self, keys: &[u64]) -> u64 {
+        let map = self.lock().unwrap();
+        keys.iter().filter_map(|key| map.get(key)).sum()
+    }
+
+    fn write(&self, key: u64, value: u64) {
+        self.lock().unwrap().insert(key, value);
+    }
+}
+
+// Keys in the benchmarked map, and how many each read looks up
+const BENCH_KEYS: u64 = 1024;
+const BENCH_READ_BATCH: usize = 32;
+
+/**
+ * Run a fixed mixed workload against `map` and return operations per second
+ * 
+ * Every thread performs `ops_per_thread` operations; `write_percent` of
+ * them are single-key writes, the rest read BENCH_READ_BATCH keys under
+ * one lock so that a read holds the lock long enough for shared access
+ * to pay off. The operation sequence is seeded per thread, so every run
+ * (and both lock types) performs exactly the same work; only the timing
+ * varies.
+ * 
+ * @param map The map under test, pre-filled with BENCH_KEYS keys
+ * @param threads Number of worker threads
+ * @param ops_per_thread Operations each thread performs
+ * @param write_percent Share of writes, 0-100
+ * @return Throughput over all threads
+ */
+fn bench_workload<M: BenchMap>(map: &M, threads: usize, ops_per_thread: usize, write_percent: u64) -> f64 {
+    let start = Instant::now();
+    thread::scope(|s| {
+        for t in 0..threads {
+            s.spawn(move || {
+                let mut state = 0x9E37_79B9_7F4A_7C15 ^ (t as u64 + 1);
+                let mut keys = [0u64; BENCH_READ_BATCH];
+                let mut checksum = 0u64;
+                for _ in 0..ops_per_thread {
+                    if next_random(&mut state) % 100 < write_percent {
+                        let key = next_random(&mut state) % BENCH_KEYS;
+                        map.write(key, state % BENCH_KEYS);
+                    } else {
+                        for key in keys.iter_mut() {
+                            *key = next_random(&mut state) % BENCH_KEYS;
+                        }
+                        checksum = checksum.wrapping_add(map.read_batch(&keys));
+                    }
+                }
+                // Returned so the reads cannot be optimised away
+                checksum
+            });
+        }
+    });
+    (threads * ops_per_thread) as f64 / start.elapsed().as_secs_f64()
+}
+
+/**
+ * Compare RwLock and Mutex on the same workload
+ * 
+ * @param threads Number of worker threads
+ * @param ops_per_thread Operations each thread performs
+ * @param write_percent Share of writes, 0-100
+ * @return (RwLock ops/s, Mutex ops/s)
+ */
+fn compare_locks(threads: usize, ops_per_thread: usize, write_percent: u64) -> (f64, f64) {
+    let initial: HashMap<u64, u64> = (0..BENCH_KEYS).map(|key| (key, key)).collect();
+    let rwlock = RwLock::new(initial.clone());
+    let mutex = Mutex::new(initial);
+    (
+        bench_workload(&rwlock, threads, ops_per_thread, write_percent),
+        bench_workload(&mutex, threads, ops_per_thread, write_percent),
+    )
+}
+
+/**
+ * Full benchmark, run with --bench
+ * 
+ * Sweeps thread counts and write ratios so the crossover is visible:
+ * with one thread, or many writes, the Mutex's cheaper locking wins;
+ * with several threads and mostly reads the RwLock pulls ahead. The
+ * threads only overlap on a multi-core machine; on a single core both
+ * lock types measure about the same.
+ */
+fn run_lock_benchmarks() {
+    println!("{:>7} {:>7} {:>14} {:>14} {:>7}", "threads", "writes", "RwLock ops/s", "Mutex ops/s", "ratio");
+    for &threads in &[1, 2, 4, 8] {
+        for &write_percent in &[0, 1, 10, 50] {
+            let (rwlock, mutex) = compare_locks(threads, 20_000, write_percent);
+            println!(
+                "{:>7} {:>6}% {:>14.0} {:>14.0} {:>7.2}",
+                threads,
+                write_percent,
+                rwlock,
+                mutex,
+                rwlock / mutex
+            );
+        }
+    }
+}
+
+/**
+ * Benchmark smoke run
+ * 
+ * Tiny parameters, just enough to exercise both lock types; run the
+ * binary with --bench for meaningful numbers
+ */
+fn demo_lock_benchmark() {
+    let (rwlock, mutex) = compare_locks(2, 200, 10);
+    println!("both workloads completed: {}", rwlock > 0.0 && mutex > 0.0);
+}
+
+/**
+ * Shape and timing of the reader-writer demo
+ * 
+ * The writers split keys key0..key{keys-1} between them and write their
+ * share one key per writer_delay; every reader looks up read_iters keys,
+ * cycling through the same names, one per reader_delay. Readers faster
+ * than the writers run into keys that have not been written yet and
+ * report "Not found"; a reader_start_delay longer than the writers need
+ * avoids that.
+ */
+#[derive(Clone)]
+struct DemoConfig {
+    writers: usize,
+    readers: usize,
+    keys: usize,
+    writer_delay: Duration,
+    reader_delay: Duration,
+    read_iters: usize,
+    // How long readers wait before their first read
+    reader_start_delay: Duration,
+    // Print every read and write as it happens
+    verbose: bool,
+    // Setting this ends the workers at their next loop check or sleep
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for DemoConfig {
+    /**
+     * The original demo: one writer every 100ms, three readers every 50ms
+     */
+    fn default() -> Self {
+        DemoConfig {
+            writers: 1,
+            readers: 3,
+            keys: 5,
+            writer_delay: Duration::from_millis(100),
+            reader_delay: Duration::from_millis(50),
+            read_iters: 10,
+            reader_start_delay: Duration::ZERO,
+            verbose: true,
+            stop: Arc::new(AtomicBool::new(false)),
+        }
+    }
+}
+
+/**
+ * Run the reader-writer demo on `cache`
+ * 
+ * Each worker checks the stop flag at the top of every iteration and
+ * sleeps with interruptible_sleep, so once it is set all of them exit,
+ * and this function returns, within about SLEEP_SLICE. Left alone the
+ * workers run their fixed number of iterations.
+ * 
+ * @param config Worker counts, key count and timing
+ * @param cache The cache the workers share
+ * @return Number of reads that found nothing
+ */
+fn run(config: DemoConfig, cache: Arc<ThreadSafeCache<String, String>>) -> usize {
+    assert!(config.keys > 0, "the demo needs at least one key");
+    
+    // Mutex to protect stdout from interleaved output
+    let cout_mutex = Arc::new(Mutex::new(()));
+    
+    let shutdown = CancellationToken::from_flag(Arc::clone(&config.stop));
+
+    /**
+     * Writer threads - writer w populates keys w, w + writers, ...
+     * Writes every writer_delay
+     */
+    let writers: Vec<_> = (0..config.writers)
+        .map(|w| {
+            let cache_writer = Arc::clone(&cache);
+            let cout_writer = Arc::clone(&cout_mutex);
+            let shutdown_writer = shutdown.clone();
+            let config = config.clone();
+            thread::spawn(move || {
+                for i in (w..config.keys).step_by(config.writers) {
+                    if shutdown_writer.is_cancelled() {
+                        break;
+                    }
+                    
+                    // Exclusive write - blocks all readers during this operation
+                    cache_writer.write(
+                        format!("key{}", i),
+                        format!("value{}", i)
+                    );
+                    
+                    // Protect console output to prevent garbled text
+                    if config.verbose {
+                        let _lock = cout_writer.lock().unwrap();
+                        println!("Writer {}: {}", i, i);
+                    } // cout_mutex released here
+                    
+                    // Sleep to simulate real work and allow readers to interleave
+                    if !interruptible_sleep(config.writer_delay, &shutdown_writer) {
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    /**
+     * Reader threads - each reads the keys in turn (cycling), every reader_delay
+     * 
+     * Note: Readers faster than the writers (50ms vs 100ms by default)
+     * may find "Not found" for keys not yet written
+     */
+    let readers: Vec<_> = (0..config.readers)
+        .map(|i| {
+            let cache_reader = Arc::clone(&cache);
+            let cout_reader = Arc::clone(&cout_mutex);
+            let shutdown_reader = shutdown.clone();
+            let config = config.clone();
+            thread::spawn(move || {
+                let mut misses = 0;
+                if !interruptible_sleep(config.reader_start_delay, &shutdown_reader) {
+                    return misses;
+                }
+                for j in 0..config.read_iters {
+                    if shutdown_reader.is_cancelled() {
+                        break;
+                    }
+                    
+                    // Shared read - can run concurrently with other reads
+                    // but will block if writer holds write lock
+                    let value = cache_reader.read(&format!("key{}", j % config.keys));
+                    if value.is_none() {
+                        misses += 1;
+                    }
+                    
+                    // Protect console output
+                    if config.verbose {
+                        let _lock = cout_reader.lock().unwrap();
+                        println!("Reader {}: {}", i, value.as_deref().unwrap_or("Not found"));
+                    } // cout_mutex released here
+                    
+                    // Sleep between reads, racing the writers
+                    if !interruptible_sleep(config.reader_delay, &shutdown_reader) {
+                        break;
+                    }
+                }
+                misses
+            })
+        })
+        .collect();
+
+    // Wait for the writers to complete
+    for writer in writers {
+        writer.join().unwrap();
+    }
+    
+    // Wait for all readers to complete
+    readers.into_iter().map(|reader| reader.join().unwrap()).sum()
+}
+
+fn main() {
+    if std::env::args().any(|arg| arg == "--bench") {
+        run_lock_benchmarks();
+        return;
+    }
+
+    // Arc (Atomic Reference Counting) allows shared ownership across threads
+    run(DemoConfig::default(), Arc::new(ThreadSafeCache::new()));
+
+    println!("\n=== Demo Configurations ===");
+    demo_config();
+
+    println!("\n=== Cooperative Shutdown ===");
+    demo_cooperative_shutdown();
+
+    println!("\n=== Generic Keys and Values ===");
+    demo_generic_types();
+
+    println!("\n=== Cancellable Sleep ===");
+    demo_interruptible_sleep();
+
+    println!("\n=== Non-Blocking Read ===");
+    demo_try_read();
+
+    println!("\n=== Double-Checked Read-Through ===");
+    demo_get_or_insert_with();
+
+    println!("\n=== Hit/Miss Statistics ===");
+    demo_stats();
+
+    println!("\n=== LRU Capacity Bound ===");
+    demo_with_capacity();
+
+    println!("\n=== CLOCK Eviction ===");
+    demo_clock_eviction();
+
+    println!("\n=== Eviction Policies ===");
+    demo_eviction_policies();
+
+    println!("\n=== Reject on Full ===");
+    demo_reject_on_full();
+
+    println!("\n=== Validated Writes ===");
+    demo_validator();
+
+    println!("\n=== Time-to-Live and Time-to-Idle ===");
+    demo_expiration();
+
+    println!("\n=== Fair Lock ===");
+    demo_fairness();
+
+    println!("\n=== Writer Escalation ===");
+    demo_writer_escalation();
+
+    println!("\n=== Pinned Keys ===");
+    demo_pinning();
+
+    println!("\n=== RwLock vs Mutex (smoke run) ===");
+    demo_lock_benchmark();
+
+    println!("\n=== O(1) Concurrent LRU ===");
+    demo_concurrent_lru();
+
+    println!("\n=== Churn Statistics ===");
+    demo_churn();
+
+    println!("\n=== Sharded Counter ===");
+    demo_sharded_counter();
+
+    println!("\n=== Save and Load ===");
+    demo_save_and_load();
+
+    println!("\n=== Batch Write ===");
+    demo_write_many();
+
+    println!("\n=== Bulk Load ===");
+    demo_bulk_load();
+
+    println!("\n=== Read With Fallback ===");
+    demo_read_or_else();
+
+    println!("\n=== Coalesced Loads With Timeout ===");
+    demo_coalesced_load();
+
+    println!("\n=== Bloom Filter ===");
+    demo_bloom_filter();
+
+    println!("\n=== Existence and Prefix Counts ===");
+    demo_contains_and_count();
+
+    println!("\n=== Missing Keys ===");
+    demo_missing_keys();
+
+    println!("\n=== Watching a Key ===");
+    demo_watch_key();
+
+    println!("\n=== Grouped Flush of Dirty Keys ===");
+    demo_flush_grouped();
+
+    println!("\n=== Snapshots ===");
+    demo_to_map();
+
+    println!("\n=== Generation Bump ===");
+    demo_bump_generation();
+
+    println!("\n=== Removal ===");
+    demo_remove_many();
+
+    println!("\n=== Clear and Count ===");
+    demo_clear_returning_count();
+
+    println!("\n=== Insertion Order ===");
+    demo_iter_ordered();
+
+    println!("\n=== Versioned Updates ===");
+    demo_versioned_update();
+
+    println!("\n=== Blocking Take ===");
+    demo_take_blocking();
+
+    println!("\n=== Waiting for a Key ===");
+    demo_wait_for();
+
+    println!("\n=== Interrupting Blocked Operations ===");
+    demo_interrupter();
+
+    println!("\n=== Atomic Update ===");
+    demo_update();
+
+    println!("\n=== Merge on Write ===");
+    demo_write_merge();
+
+    println!("\n=== Parallel Reduce ===");
+    demo_par_reduce();
+
+    println!("\n=== Offloaded Reads ===");
+    demo_read_async();
+
+    println!("\n=== Health Check ===");
+    demo_health_check();
 }
\ No newline at end of file
@@ -1,4 +1,10 @@
-use std::thread;
+use std::any::Any;
+use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender, TrySendError};
+use std::sync::{Arc, Barrier, Mutex, Once};
+use std::thread::{self, JoinHandle};
 use std::time::Duration;
 
 // Simple worker function
@@ -12,22 +18,572 @@ fn calculate_sum(start: i32, end: i32) -> i32 {
     (start..=end).sum()
 }
 
+// Spawns a thread with a name, which panic messages and debuggers show
+// instead of '<unnamed>'. Unlike thread::spawn this reports a failure to
+// create the thread (e.g. the OS is out of threads) instead of panicking.
+fn spawn_named<F, T>(name: &str, f: F) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new().name(name.to_string()).spawn(f)
+}
+
+// spawn_named with an explicit stack size in bytes, for workers that
+// recurse deeper than the default stack (2 MiB on most platforms) allows
+fn spawn_named_with_stack<F, T>(name: &str, stack_size: usize, f: F) -> io::Result<JoinHandle<T>>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    thread::Builder::new().name(name.to_string()).stack_size(stack_size).spawn(f)
+}
+
+// Spawns `f`, retrying up to `max_retries` times with `backoff` between
+// attempts when the OS refuses to create a thread (e.g. EAGAIN at the
+// thread limit). Gives up with the last error.
+fn spawn_with_retry<F: FnOnce() + Send + 'static>(f: F, max_retries: usize, backoff: Duration) -> io::Result<JoinHandle<()>> {
+    spawn_with_retry_using(f, max_retries, backoff, |job| thread::Builder::new().spawn(job))
+}
+
+// spawn_with_retry with the actual spawning supplied by the caller, so a
+// named Builder can be used, or a failing spawner injected to exercise
+// the retry path. Builder::spawn consumes its closure even when it fails,
+// so `f` is parked in a shared slot and every attempt only gets a small
+// trampoline that takes it out of the slot when the thread starts.
+fn spawn_with_retry_using<F, S>(f: F, max_retries: usize, backoff: Duration, mut spawn: S) -> io::Result<JoinHandle<()>>
+where
+    F: FnOnce() + Send + 'static,
+    S: FnMut(Job) -> io::Result<JoinHandle<()>>,
+{
+    let slot = Arc::new(Mutex::new(Some(f)));
+    let mut retries = 0;
+    loop {
+        let thread_slot = Arc::clone(&slot);
+        let trampoline: Job = Box::new(move || {
+            // Take it out first so the slot is not locked while `f` runs
+            let f = thread_slot.lock().unwrap().take();
+            if let Some(f) = f {
+                f();
+            }
+        });
+        
+        match spawn(trampoline) {
+            Ok(handle) => return Ok(handle),
+            Err(_) if retries < max_retries => {
+                retries += 1;
+                thread::sleep(backoff);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Recurses `n` frames deep and returns `n`; the local buffer makes each
+// frame large enough for the depth to matter
+fn deep_recursion(n: u64) -> u64 {
+    let buffer = [1u64; 64];
+    if n == 0 {
+        0
+    } else {
+        buffer[(n % 64) as usize] + deep_recursion(n - 1)
+    }
+}
+
+// `threads` threads each add 1 to a Mutex-guarded counter `per_thread` times
+fn count_with_mutex(threads: usize, per_thread: usize) -> usize {
+    let counter = Arc::new(Mutex::new(0));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..per_thread {
+                    *counter.lock().unwrap() += 1;
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    let total = *counter.lock().unwrap();
+    total
+}
+
+// Same work on an AtomicUsize: no lock, each increment is one atomic
+// instruction. Relaxed suffices because only the final total matters and
+// join() makes every increment visible to the thread reading it.
+fn count_with_atomic(threads: usize, per_thread: usize) -> usize {
+    let counter = Arc::new(AtomicUsize::new(0));
+    let handles: Vec<_> = (0..threads)
+        .map(|_| {
+            let counter = Arc::clone(&counter);
+            thread::spawn(move || {
+                for _ in 0..per_thread {
+                    counter.fetch_add(1, Ordering::Relaxed);
+                }
+            })
+        })
+        .collect();
+    for handle in handles {
+        handle.join().unwrap();
+    }
+    counter.load(Ordering::Relaxed)
+}
+
+// Applies `f` to every item on scoped threads, one contiguous chunk per
+// available core, and returns the results in input order: each thread
+// maps its chunk into its own Vec and the chunks are joined in order, no
+// matter which thread finishes first. An empty slice spawns nothing. A
+// panic in `f` is re-raised on the calling thread with its original
+// payload.
+fn parallel_map<T: Sync, R: Send, F: Fn(&T) -> R + Sync>(items: &[T], f: F) -> Vec<R> {
+    if items.is_empty() {
+        return Vec::new();
+    }
+    
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let chunk_size = items.len().div_ceil(threads);
+    let f = &f;
+    thread::scope(|s| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| s.spawn(move || chunk.iter().map(f).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().unwrap_or_else(|payload| panic::resume_unwind(payload)))
+            .collect()
+    })
+}
+
+// Runs a side effect exactly once, no matter how many threads race to it
+struct CallOnce {
+    once: Once,
+}
+
+impl CallOnce {
+    fn new() -> Self {
+        CallOnce { once: Once::new() }
+    }
+
+    // Returns true only for the one call that actually executed `f`;
+    // callers that lose the race block until `f` has finished
+    fn run<F: FnOnce()>(&self, f: F) -> bool {
+        let mut executed = false;
+        self.once.call_once(|| {
+            f();
+            executed = true;
+        });
+        executed
+    }
+}
+
+// Extracts the message from a panic payload (panic! with a literal or format!)
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg.to_string()
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+// Joins every handle, even after one of them has panicked, and returns
+// the positions of the threads that panicked (in increasing order).
+// Calling join().unwrap() in a loop would instead re-raise the first panic
+// and leave the remaining threads unjoined.
+fn join_all(handles: Vec<JoinHandle<()>>) -> Vec<usize> {
+    handles
+        .into_iter()
+        .enumerate()
+        .filter_map(|(i, handle)| handle.join().err().map(|_| i))
+        .collect()
+}
+
+// Why a task collected by run_collect_errors did not produce a value
+#[derive(Debug)]
+enum TaskError<E> {
+    // The task returned Err
+    Failed(E),
+    // The task panicked; holds the panic message
+    Panicked(String),
+}
+
+// Runs every task on its own thread and waits for all of them, instead of
+// stopping at the first failure. Successes and errors are returned in task
+// order; a task that panics is reported as TaskError::Panicked, so E needs
+// no conversion from the panic message.
+fn run_collect_errors<T, E, F>(tasks: Vec<F>) -> (Vec<T>, Vec<TaskError<E>>)
+where
+    F: FnOnce() -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: Send + 'static,
+{
+    let handles: Vec<_> = tasks.into_iter().map(thread::spawn).collect();
+    
+    let mut successes = Vec::new();
+    let mut errors = Vec::new();
+    for handle in handles {
+        match handle.join() {
+            Ok(Ok(value)) => successes.push(value),
+            Ok(Err(error)) => errors.push(TaskError::Failed(error)),
+            Err(payload) => errors.push(TaskError::Panicked(panic_message(payload))),
+        }
+    }
+    (successes, errors)
+}
+
+// Bounded pipeline: one producer sends `items` work items through a
+// sync_channel holding at most `bound` of them, `consumers` threads share
+// the receiver and each returns the items it handled. Once the queue is
+// full the producer blocks in send() until a consumer makes room
+// (backpressure); the number of times that happened is returned too.
+fn produce_consume(items: u32, bound: usize, consumers: usize) -> (Vec<Vec<u32>>, usize) {
+    let (sender, receiver) = mpsc::sync_channel::<u32>(bound);
+    let receiver = Arc::new(Mutex::new(receiver));
+    
+    let producer = thread::spawn(move || {
+        let mut blocked = 0;
+        for item in 0..items {
+            // try_send first only to count how often the queue was full
+            if let Err(TrySendError::Full(item)) = sender.try_send(item) {
+                blocked += 1;
+                sender.send(item).unwrap();
+            }
+        }
+        // Dropping the sender lets the consumers' recv() fail and end
+        blocked
+    });
+    
+    let handles: Vec<_> = (0..consumers)
+        .map(|_| {
+            let receiver = Arc::clone(&receiver);
+            thread::spawn(move || {
+                let mut handled = Vec::new();
+                loop {
+                    // The guard is a temporary, released before the item is processed
+                    let item = receiver.lock().unwrap().recv();
+                    match item {
+                        Ok(item) => {
+                            thread::sleep(Duration::from_millis(5));
+                            handled.push(item);
+                        }
+                        Err(_) => break,
+                    }
+                }
+                handled
+            })
+        })
+        .collect();
+    
+    let blocked = producer.join().unwrap();
+    let handled = handles.into_iter().map(|h| h.join().unwrap()).collect();
+    (handled, blocked)
+}
+
+type Job = Box<dyn FnOnce() + Send + 'static>;
+
+// When a ThreadPool starts its workers
+#[derive(Clone, Copy, Debug)]
+enum SpawnPolicy {
+    // Every worker at construction, all kept until the pool is dropped
+    Eager,
+    // `min_alive` workers at construction, the others only once a job
+    // finds no idle worker. Those extra workers exit after `keep_alive`
+    // without a job, so the pool shrinks back to `min_alive` when idle.
+    Lazy { min_alive: usize, keep_alive: Duration },
+}
+
+// Worker threads fed from one job queue, so running a task does not cost
+// a fresh OS thread. Dropping the pool closes the queue; the workers
+// finish the jobs already queued, then exit and are joined.
+struct ThreadPool {
+    shared: Arc<PoolShared>,
+    workers: Mutex<Workers>,
+    // None once the pool is shutting down
+    sender: Option<Sender<Job>>,
+}
+
+// State the pool shares with its workers
+struct PoolShared {
+    // mpsc has a single consumer, so the workers share the receiver
+    receiver: Mutex<Receiver<Job>>,
+    policy: SpawnPolicy,
+    // Most workers live at once
+    max: usize,
+    // Workers started and not yet exited
+    live: AtomicUsize,
+    // Live workers waiting for a job
+    idle: AtomicUsize,
+    // Jobs sent but not yet taken by a worker
+    pending: AtomicUsize,
+}
+
+struct Worker {
+    handle: JoinHandle<()>,
+    // Jobs this worker has taken off the queue
+    jobs: Arc<AtomicU64>,
+}
+
+// The pool's record of its worker threads
+struct Workers {
+    // Running, or retired by keep-alive but not joined yet
+    held: Vec<Worker>,
+    // Jobs taken by the retired workers already joined and dropped from
+    // `held`; None until the first one is
+    retired_jobs: Option<u64>,
+}
+
+impl ThreadPool {
+    // Panics if `size` is zero: a pool without workers would never run a job
+    fn new(size: usize) -> Self {
+        Self::with_policy(size, SpawnPolicy::Eager)
+    }
+
+    // A pool of at most `size` workers, started as `policy` says.
+    // Panics if `size` is zero or below a lazy pool's `min_alive`.
+    fn with_policy(size: usize, policy: SpawnPolicy) -> Self {
+        assert!(size > 0, "ThreadPool needs at least one worker thread");
+        let prespawned = match policy {
+            SpawnPolicy::Eager => size,
+            SpawnPolicy::Lazy { min_alive, .. } => {
+                assert!(min_alive <= size, "ThreadPool cannot keep more workers alive than its size");
+                min_alive
+            }
+        };
+        
+        let (sender, receiver) = mpsc::channel::<Job>();
+        let shared = Arc::new(PoolShared {
+            receiver: Mutex::new(receiver),
+            policy,
+            max: size,
+            live: AtomicUsize::new(0),
+            idle: AtomicUsize::new(0),
+            pending: AtomicUsize::new(0),
+        });
+        let pool = ThreadPool {
+            shared,
+            workers: Mutex::new(Workers { held: Vec::new(), retired_jobs: None }),
+            sender: Some(sender),
+        };
+        for _ in 0..prespawned {
+            pool.spawn_worker();
+        }
+        pool
+    }
+
+    // Queues `job` to run on the next free worker, starting a new worker
+    // first if every live one is busy and the pool is below its size
+    fn execute<F: FnOnce() + Send + 'static>(&self, job: F) {
+        // Counted before sending, so the worker that takes the job never
+        // sees the counter below it
+        self.shared.pending.fetch_add(1, Ordering::SeqCst);
+        self.sender
+            .as_ref()
+            .expect("pool is running until dropped")
+            .send(Box::new(job))
+            .expect("workers outlive the sender");
+        if self.shared.pending.load(Ordering::SeqCst) > self.shared.idle.load(Ordering::SeqCst) {
+            self.spawn_worker();
+        }
+    }
+
+    // Queues `job` and returns a handle to its result. The worker sends the
+    // value into a channel of its own; if the handle was dropped in the
+    // meantime the send just fails and the value is discarded, so an
+    // unread result neither blocks the worker nor lingers in the pool.
+    fn submit<T, F>(&self, job: F) -> JobHandle<T>
+    where
+        T: Send + 'static,
+        F: FnOnce() -> T + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+        self.execute(move || {
+            let _ = sender.send(job());
+        });
+        JobHandle { receiver }
+    }
+
+    // Workers currently running or waiting for a job
+    fn live_workers(&self) -> usize {
+        self.shared.live.load(Ordering::SeqCst)
+    }
+
+    // How many jobs each worker has taken off the queue so far, panicked
+    // ones included, in the order the workers were started. Workers the
+    // pool has already joined after keep-alive retired them are summed
+    // into one last entry, so the counts always add up to every job
+    // taken. A snapshot: jobs still queued are not counted yet.
+    fn worker_job_counts(&self) -> Vec<u64> {
+        let workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        workers
+            .held
+            .iter()
+            .map(|worker| worker.jobs.load(Ordering::Relaxed))
+            .chain(workers.retired_jobs)
+            .collect()
+    }
+
+    // Starts one more worker unless `max` are already live
+    fn spawn_worker(&self) {
+        let max = self.shared.max;
+        let reserved = self.shared.live.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| {
+            if live < max { Some(live + 1) } else { None }
+        });
+        if reserved.is_err() {
+            return;
+        }
+        
+        let jobs = Arc::new(AtomicU64::new(0));
+        let shared = Arc::clone(&self.shared);
+        let job_count = Arc::clone(&jobs);
+        let handle = thread::spawn(move || shared.work(&job_count));
+        
+        let mut workers = self.workers.lock().unwrap_or_else(|e| e.into_inner());
+        // Join the workers keep-alive retired, so a pool that keeps idling
+        // and respawning does not pile up their handles
+        let (finished, running): (Vec<Worker>, Vec<Worker>) =
+            workers.held.drain(..).partition(|worker| worker.handle.is_finished());
+        workers.held = running;
+        for worker in finished {
+            worker.handle.join().unwrap();
+            *workers.retired_jobs.get_or_insert(0) += worker.jobs.load(Ordering::Relaxed);
+        }
+        workers.held.push(Worker { handle, jobs });
+    }
+}
+
+impl PoolShared {
+    // Worker loop: the lock is released before the job runs, so other
+    // workers can pick up jobs meanwhile. recv() fails once the sender
+    // is dropped and the queue is empty, which ends the loop; a lazily
+    // spawned worker also ends it after waiting `keep_alive` in vain.
+    fn work(&self, job_count: &AtomicU64) {
+        loop {
+            self.idle.fetch_add(1, Ordering::SeqCst);
+            let job = {
+                let receiver = self.receiver.lock().unwrap_or_else(|e| e.into_inner());
+                match self.policy {
+                    SpawnPolicy::Eager => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+                    SpawnPolicy::Lazy { keep_alive, .. } => receiver.recv_timeout(keep_alive),
+                }
+            };
+            self.idle.fetch_sub(1, Ordering::SeqCst);
+            match job {
+                // A panicking job must not take its worker down with it
+                Ok(job) => {
+                    self.pending.fetch_sub(1, Ordering::SeqCst);
+                    job_count.fetch_add(1, Ordering::Relaxed);
+                    let _ = panic::catch_unwind(AssertUnwindSafe(job));
+                }
+                Err(RecvTimeoutError::Timeout) => {
+                    if self.retire() {
+                        return;
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        }
+        self.live.fetch_sub(1, Ordering::SeqCst);
+    }
+
+    // Called by an idle worker whose keep-alive ran out; true if it may
+    // exit. It never takes the pool below `min_alive`, and it stays if a
+    // job arrived meanwhile: execute() bumps `pending` before checking
+    // `idle`, the worker drops `idle` (before calling this) and `live`
+    // before checking `pending`, so either the worker sees the job or
+    // execute() sees the freed slot and starts a replacement.
+    fn retire(&self) -> bool {
+        let min_alive = match self.policy {
+            SpawnPolicy::Eager => return false,
+            SpawnPolicy::Lazy { min_alive, .. } => min_alive,
+        };
+        let left = self.live.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| {
+            if live > min_alive { Some(live - 1) } else { None }
+        });
+        if left.is_err() {
+            return false;
+        }
+        if self.pending.load(Ordering::SeqCst) == 0 {
+            return true;
+        }
+        // Take the slot back, unless a replacement already has it
+        let max = self.max;
+        self.live
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |live| if live < max { Some(live + 1) } else { None })
+            .is_err()
+    }
+}
+
+// The pending result of a job given to ThreadPool::submit
+struct JobHandle<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> JobHandle<T> {
+    // Blocks until the job has run; None if it panicked (its sender was
+    // dropped without sending)
+    fn join(self) -> Option<T> {
+        self.receiver.recv().ok()
+    }
+}
+
+impl Drop for ThreadPool {
+    fn drop(&mut self) {
+        // Closing the channel is the shutdown signal
+        drop(self.sender.take());
+        let workers = self.workers.get_mut().unwrap_or_else(|e| e.into_inner());
+        for worker in workers.held.drain(..) {
+            worker.handle.join().unwrap();
+        }
+    }
+}
+
 fn main() {
     println!("=== Basic Pattern with JoinHandle ===");
     
     // Pattern 1: Simple thread creation and joining
     {
-        let handles: Vec<_> = (0..5)
+        // Each spawn is retried a few times; collect() then stops at the first
+        // spawn that still fails, detaching the threads already started
+        let handles: io::Result<Vec<_>> = (0..5)
             .map(|i| {
-                thread::spawn(move || {
-                    worker(i, "Hello from thread");
+                let name = format!("worker-{}", i);
+                spawn_with_retry_using(
+                    move || worker(i, "Hello from thread"),
+                    3,
+                    Duration::from_millis(10),
+                    |job| thread::Builder::new().name(name.clone()).spawn(job),
+                )
+            })
+            .collect();
+        
+        match handles {
+            // Join all threads, a panic in one does not abandon the others
+            Ok(handles) => println!("Panicked threads: {:?}", join_all(handles)),
+            Err(e) => println!("Could not spawn workers: {}", e),
+        }
+    }
+    
+    // Pattern 1b: Some threads panic, all are still joined
+    {
+        println!("(the panic messages below are expected)");
+        let handles: io::Result<Vec<JoinHandle<()>>> = (0..5)
+            .map(|i| {
+                spawn_named(&format!("worker-{}", i), move || {
+                    if i % 2 == 1 {
+                        panic!("thread {} failed", i);
+                    }
+                    worker(i, "Finished despite failing siblings");
                 })
             })
             .collect();
         
-        // Join all threads
-        for handle in handles {
-            handle.join().unwrap();
+        match handles {
+            Ok(handles) => println!("Panicked threads: {:?} (expected [1, 3])", join_all(handles)),
+            Err(e) => println!("Could not spawn workers: {}", e),
         }
     }
     
@@ -92,21 +648,63 @@ fn main() {
         
         // data is still available here!
         println!("Original data still accessible: {:?}", data);
+        
+        // Results collected back per element, in input order
+        println!("Doubled in parallel: {:?}", parallel_map(&data, |x| x * 2));
+        println!("Empty input: {:?}", parallel_map(&[] as &[i32], |x| x * 2));
+        
+        println!("(the panic message below is expected)");
+        let result = panic::catch_unwind(|| {
+            parallel_map(&data, |&x| {
+                if x == 3 {
+                    panic!("cannot map {}", x);
+                }
+                x
+            })
+        });
+        match result {
+            Ok(values) => println!("Unexpectedly mapped: {:?}", values),
+            Err(payload) => println!("Panic surfaced from the scope: {}", panic_message(payload)),
+        }
     }
     
     println!("\n=== Shared State with Mutex ===");
     
     // Pattern 5: Sharing mutable state safely
     {
-        use std::sync::{Arc, Mutex};
-        
         let counter = Arc::new(Mutex::new(0));
+        let handles: io::Result<Vec<_>> = (0..5)
+            .map(|i| {
+                let counter = Arc::clone(&counter);
+                spawn_named(&format!("counter-{}", i), move || {
+                    let mut num = counter.lock().unwrap();
+                    *num += 1;
+                })
+            })
+            .collect();
+        
+        match handles {
+            Ok(handles) => {
+                for handle in handles {
+                    handle.join().unwrap();
+                }
+            }
+            Err(e) => println!("Could not spawn counters: {}", e),
+        }
+        
+        println!("Final counter value: {}", *counter.lock().unwrap());
+    }
+    
+    println!("\n=== Shared Counter with an Atomic ===");
+    
+    // Pattern 5b: A lone integer needs no Mutex, an atomic does the same job
+    {
+        let counter = Arc::new(AtomicUsize::new(0));
         let handles: Vec<_> = (0..5)
             .map(|_| {
                 let counter = Arc::clone(&counter);
                 thread::spawn(move || {
-                    let mut num = counter.lock().unwrap();
-                    *num += 1;
+                    counter.fetch_add(1, Ordering::Relaxed);
                 })
             })
             .collect();
@@ -115,6 +713,293 @@ fn main() {
             handle.join().unwrap();
         }
         
-        println!("Final counter value: {}", *counter.lock().unwrap());
+        println!("Final counter value: {}", counter.load(Ordering::Relaxed));
+        
+        // A Mutex is only needed once several values must change together
+        let (with_mutex, with_atomic) = (count_with_mutex(32, 1000), count_with_atomic(32, 1000));
+        println!("32 threads x 1000: Mutex {}, atomic {}, equal: {}", with_mutex, with_atomic, with_mutex == with_atomic);
+    }
+    
+    println!("\n=== Run Exactly Once ===");
+    
+    // Pattern 6: A startup banner printed by whichever thread gets there first
+    {
+        let banner = Arc::new(CallOnce::new());
+        let printed = Arc::new(AtomicUsize::new(0));
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let banner = Arc::clone(&banner);
+                let printed = Arc::clone(&printed);
+                thread::spawn(move || {
+                    banner.run(|| {
+                        println!("Startup banner printed by thread {}", i);
+                        printed.fetch_add(1, Ordering::SeqCst);
+                    })
+                })
+            })
+            .collect();
+        
+        let winners = handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .filter(|&ran| ran)
+            .count();
+        
+        println!("Threads that ran the closure: {}", winners);
+        println!("Banner printed {} time(s)", printed.load(Ordering::SeqCst));
+    }
+    
+    println!("\n=== Collecting Errors From All Threads ===");
+    
+    // Pattern 7: Every task runs to completion, failures are gathered, not propagated
+    {
+        type Task = Box<dyn FnOnce() -> Result<i32, String> + Send>;
+        
+        let tasks: Vec<Task> = vec![
+            Box::new(|| Ok(calculate_sum(1, 10))),
+            Box::new(|| Err("invalid range".to_string())),
+            Box::new(|| panic!("worker crashed")),
+            Box::new(|| Ok(calculate_sum(11, 20))),
+        ];
+        
+        let (results, errors) = run_collect_errors(tasks);
+        println!("Successes: {:?}", results);
+        for error in errors {
+            match error {
+                TaskError::Failed(reason) => println!("Task failed: {}", reason),
+                TaskError::Panicked(message) => println!("Task panicked: {}", message),
+            }
+        }
+    }
+    
+    println!("\n=== Named Threads ===");
+    
+    // Pattern 8: Names show up in thread::current() and in panic messages
+    {
+        let named = spawn_named("named-worker", || {
+            let name = thread::current().name().map(String::from);
+            panic!("{:?} gave up", name);
+        });
+        match named.map(|handle| handle.join()) {
+            Ok(Ok(())) => println!("named-worker did not panic"),
+            Ok(Err(payload)) => println!("Panic from the named thread: {}", panic_message(payload)),
+            Err(e) => println!("Could not spawn named-worker: {}", e),
+        }
+        
+        // A 32 MiB stack for recursion the default stack would overflow on
+        let deep = spawn_named_with_stack("deep-recursion", 32 * 1024 * 1024, || deep_recursion(50_000));
+        match deep.map(|handle| handle.join()) {
+            Ok(Ok(result)) => println!("Deep recursion finished: {}", result),
+            Ok(Err(payload)) => println!("Deep recursion panicked: {}", panic_message(payload)),
+            Err(e) => println!("Could not spawn deep-recursion: {}", e),
+        }
+    }
+    
+    println!("\n=== Retrying a Failed Spawn ===");
+    
+    // Pattern 8b: A spawner that fails twice, as the OS does at its thread limit
+    {
+        let ran = Arc::new(AtomicBool::new(false));
+        let job_ran = Arc::clone(&ran);
+        let mut attempts = 0;
+        let handle = spawn_with_retry_using(
+            move || job_ran.store(true, Ordering::SeqCst),
+            3,
+            Duration::from_millis(5),
+            |job| {
+                attempts += 1;
+                if attempts <= 2 {
+                    Err(io::Error::new(io::ErrorKind::WouldBlock, "Resource temporarily unavailable"))
+                } else {
+                    thread::Builder::new().spawn(job)
+                }
+            },
+        );
+        let joined = handle.map(|handle| handle.join().is_ok());
+        println!("Spawned after {} attempts: {:?}, job ran: {}", attempts, joined, ran.load(Ordering::SeqCst));
+        
+        // Out of retries: the last error is returned
+        let mut attempts = 0;
+        let result = spawn_with_retry_using(|| {}, 2, Duration::ZERO, |_| {
+            attempts += 1;
+            Err(io::Error::new(io::ErrorKind::WouldBlock, format!("attempt {} failed", attempts)))
+        });
+        println!("Gave up after {} attempts: {}", attempts, result.err().map_or(String::new(), |e| e.to_string()));
+        
+        // The real thing, which normally succeeds on the first try
+        let handle = spawn_with_retry(|| println!("Hello from a retried spawn"), 3, Duration::from_millis(10));
+        println!("Plain spawn_with_retry joined cleanly: {}", handle.map(|h| h.join().is_ok()).unwrap_or(false));
+    }
+    
+    println!("\n=== Bounded Producer/Consumer ===");
+    
+    // Pattern 9: A channel pipeline with backpressure
+    {
+        let (handled, blocked) = produce_consume(20, 2, 3);
+        for (i, items) in handled.iter().enumerate() {
+            println!("Consumer {} handled {:?}", i, items);
+        }
+        
+        let mut all: Vec<u32> = handled.into_iter().flatten().collect();
+        all.sort_unstable();
+        println!("Every item consumed exactly once: {}", all == (0..20).collect::<Vec<_>>());
+        println!("Producer blocked on a full queue {} time(s)", blocked);
+    }
+    
+    println!("\n=== Thread Pool ===");
+    
+    // Pattern 10: Many tasks on a few reusable threads
+    {
+        let pool = ThreadPool::new(3);
+        for i in 0..5 {
+            pool.execute(move || worker(i, "Hello from the pool"));
+        }
+        
+        // Results come back through handles, like joining a thread
+        let sums: Vec<JobHandle<i32>> = (0..3)
+            .map(|i| pool.submit(move || calculate_sum(i * 10, (i + 1) * 10)))
+            .collect();
+        for (i, sum) in (0..3).zip(sums) {
+            let sum = sum.join();
+            println!(
+                "Pooled sum {}: {:?} (matches direct: {})",
+                i,
+                sum,
+                sum == Some(calculate_sum(i * 10, (i + 1) * 10))
+            );
+        }
+        // A handle dropped unread does not hold up the worker
+        drop(pool.submit(|| calculate_sum(1, 100)));
+        
+        let counter = Arc::new(AtomicUsize::new(0));
+        for _ in 0..100 {
+            let counter = Arc::clone(&counter);
+            pool.execute(move || {
+                counter.fetch_add(1, Ordering::SeqCst);
+            });
+        }
+        
+        // Dropping the pool waits for every queued job
+        drop(pool);
+        println!("Counter after pool shutdown: {} (expected 100)", counter.load(Ordering::SeqCst));
+        
+        // Uniform jobs that take a moment each get spread over all workers
+        let pool = ThreadPool::new(4);
+        let done: Vec<JobHandle<()>> = (0..40)
+            .map(|_| pool.submit(|| thread::sleep(Duration::from_millis(2))))
+            .collect();
+        for handle in done {
+            handle.join();
+        }
+        let counts = pool.worker_job_counts();
+        println!(
+            "Jobs per worker: {:?} (total {}, every worker busy: {})",
+            counts,
+            counts.iter().sum::<u64>(),
+            counts.iter().all(|&count| count > 0)
+        );
+    }
+    
+    // Pattern 10b: Workers started only as jobs arrive, retired when idle
+    {
+        let pool = ThreadPool::with_policy(
+            4,
+            SpawnPolicy::Lazy { min_alive: 0, keep_alive: Duration::from_millis(10) },
+        );
+        println!("Live workers before any job: {}", pool.live_workers());
+        
+        pool.submit(|| ()).join();
+        println!("Live workers after the first job: {}", pool.live_workers());
+        
+        // A burst of slow jobs keeps every worker busy, so more get started
+        let burst: Vec<JobHandle<()>> = (0..8)
+            .map(|_| pool.submit(|| thread::sleep(Duration::from_millis(20))))
+            .collect();
+        println!("Live workers under load: {} (max 4)", pool.live_workers());
+        for handle in burst {
+            handle.join();
+        }
+        
+        // Idle workers beyond min_alive exit once keep_alive runs out
+        thread::sleep(Duration::from_millis(200));
+        println!("Live workers after idling: {}", pool.live_workers());
+        
+        // The next burst starts fresh workers and joins the retired ones,
+        // whose counts end up in the last entry
+        let burst: Vec<JobHandle<()>> = (0..8)
+            .map(|_| pool.submit(|| thread::sleep(Duration::from_millis(20))))
+            .collect();
+        for handle in burst {
+            handle.join();
+        }
+        let counts = pool.worker_job_counts();
+        println!(
+            "Jobs per worker after a second burst: {:?} (total {}, expected 17)",
+            counts,
+            counts.iter().sum::<u64>()
+        );
+        
+        let warm = ThreadPool::with_policy(
+            4,
+            SpawnPolicy::Lazy { min_alive: 2, keep_alive: Duration::from_millis(10) },
+        );
+        thread::sleep(Duration::from_millis(50));
+        println!("Live workers kept by min_alive 2: {}", warm.live_workers());
+    }
+    
+    println!("\n=== Barrier-Synchronized Phases ===");
+    
+    // Pattern 11: No thread starts phase 2 before every thread finished phase 1
+    {
+        const WORKERS: usize = 4;
+        let barrier = Arc::new(Barrier::new(WORKERS));
+        let phase1_done = Arc::new(AtomicUsize::new(0));
+        let phase2_started = Arc::new(AtomicUsize::new(0));
+        let log = Arc::new(Mutex::new(Vec::new()));
+        
+        let handles: Vec<_> = (0..WORKERS)
+            .map(|i| {
+                let barrier = Arc::clone(&barrier);
+                let phase1_done = Arc::clone(&phase1_done);
+                let phase2_started = Arc::clone(&phase2_started);
+                let log = Arc::clone(&log);
+                thread::spawn(move || {
+                    // Phase 1: uneven amounts of work
+                    thread::sleep(Duration::from_millis(10 * (WORKERS - i) as u64));
+                    log.lock().unwrap().push(format!("worker {} finished phase 1", i));
+                    phase1_done.fetch_add(1, Ordering::SeqCst);
+                    
+                    // Nobody may be in phase 2 while this worker is still in phase 1
+                    let none_early = phase2_started.load(Ordering::SeqCst) == 0;
+                    
+                    // Blocks until all WORKERS threads have called wait()
+                    let leader = barrier.wait().is_leader();
+                    
+                    // Everyone must see the full phase-1 count once the barrier opens
+                    let complete = phase1_done.load(Ordering::SeqCst) == WORKERS;
+                    phase2_started.fetch_add(1, Ordering::SeqCst);
+                    log.lock().unwrap().push(format!(
+                        "worker {} started phase 2{}",
+                        i,
+                        if leader { " (barrier leader)" } else { "" }
+                    ));
+                    none_early && complete
+                })
+            })
+            .collect();
+        
+        // Collect first: all() would stop joining at the first false
+        let complete: Vec<bool> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        let all_complete = complete.iter().all(|&c| c);
+        
+        for line in log.lock().unwrap().iter() {
+            println!("{}", line);
+        }
+        println!("No phase 2 before the barrier, all of phase 1 done at it: {}", all_complete);
+        println!(
+            "Workers that ran phase 2: {} (expected {})",
+            phase2_started.load(Ordering::SeqCst),
+            WORKERS
+        );
     }
 }
\ No newline at end of file
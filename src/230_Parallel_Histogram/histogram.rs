@@ -0,0 +1,156 @@
+/*
+ * Parallel Histogram Demo using a shared CounterMap
+ * Compile: rustc histogram.rs
+ * Or with Cargo: cargo run
+ *
+ * Demonstrates:
+ * - Splitting input across worker threads with thread::scope
+ * - Many threads incrementing counters in one shared map
+ * - Read-lock fast path with AtomicU64 counters, write lock only for new keys
+ * - Checking the parallel result against a sequential computation
+ */
+
+use std::collections::{BTreeMap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::RwLock;
+use std::thread;
+
+/**
+ * Thread-safe map of counters
+ *
+ * Each counter is an AtomicU64, so incrementing an existing key only
+ * needs the shared read lock: workers bumping different (or the same)
+ * buckets run concurrently. The write lock is taken only the first time
+ * a key is seen, to insert its counter.
+ */
+struct CounterMap<K> {
+    counters: RwLock<HashMap<K, AtomicU64>>,
+}
+
+impl<K: Eq + Hash + Clone + Ord> CounterMap<K> {
+    /**
+     * Create an empty counter map
+     */
+    fn new() -> Self {
+        CounterMap {
+            counters: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /**
+     * Add `by` to the counter for `key`, creating it at zero if needed
+     *
+     * @param key The counter to bump
+     * @param by The amount to add
+     */
+    fn increment(&self, key: K, by: u64) {
+        // Fast path: the counter exists, a shared lock is enough
+        {
+            let counters = self.counters.read().unwrap();
+            if let Some(counter) = counters.get(&key) {
+                counter.fetch_add(by, Ordering::Relaxed);
+                return;
+            }
+        }
+
+        // Slow path: another thread may have inserted it since we looked,
+        // entry() handles both cases under the exclusive lock
+        let mut counters = self.counters.write().unwrap();
+        counters
+            .entry(key)
+            .or_insert_with(|| AtomicU64::new(0))
+            .fetch_add(by, Ordering::Relaxed);
+    }
+
+    /**
+     * Copy of all counters, ordered by key
+     *
+     * @return key -> count
+     */
+    fn snapshot(&self) -> BTreeMap<K, u64> {
+        let counters = self.counters.read().unwrap();
+        counters
+            .iter()
+            .map(|(key, counter)| (key.clone(), counter.load(Ordering::Relaxed)))
+            .collect()
+    }
+}
+
+// Width of each histogram bucket
+const BUCKET_WIDTH: u32 = 10;
+
+fn bucket_of(value: u32) -> u32 {
+    value / BUCKET_WIDTH * BUCKET_WIDTH
+}
+
+/**
+ * Build a histogram of `input` with `workers` threads
+ *
+ * Each worker takes one contiguous slice of the input and increments
+ * the shared CounterMap directly; no per-thread merging step is needed.
+ *
+ * @param input The values to count
+ * @param workers Number of worker threads (0 is treated as 1)
+ * @return bucket start -> number of values in the bucket
+ */
+fn parallel_histogram(input: &[u32], workers: usize) -> BTreeMap<u32, u64> {
+    let histogram = CounterMap::new();
+    if input.is_empty() {
+        return histogram.snapshot();
+    }
+
+    let chunk_size = input.len().div_ceil(workers.max(1));
+    thread::scope(|s| {
+        for slice in input.chunks(chunk_size) {
+            let histogram = &histogram;
+            s.spawn(move || {
+                for &value in slice {
+                    histogram.increment(bucket_of(value), 1);
+                }
+            });
+        }
+        // All workers joined at scope end
+    });
+
+    histogram.snapshot()
+}
+
+/**
+ * Reference single-threaded histogram
+ */
+fn sequential_histogram(input: &[u32]) -> BTreeMap<u32, u64> {
+    let mut histogram = BTreeMap::new();
+    for &value in input {
+        *histogram.entry(bucket_of(value)).or_insert(0) += 1;
+    }
+    histogram
+}
+
+/**
+ * Deterministic pseudo-random input in 0..100 (linear congruential generator)
+ */
+fn generate_input(len: usize) -> Vec<u32> {
+    let mut state: u32 = 12345;
+    (0..len)
+        .map(|_| {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            (state >> 16) % 100
+        })
+        .collect()
+}
+
+fn main() {
+    let input = generate_input(100_000);
+
+    let parallel = parallel_histogram(&input, 4);
+    let sequential = sequential_histogram(&input);
+
+    println!("=== Parallel Histogram (4 workers) ===");
+    for (bucket, count) in &parallel {
+        println!("{:>2}-{:>2}: {}", bucket, bucket + BUCKET_WIDTH - 1, count);
+    }
+
+    println!("\nTotal values counted: {}", parallel.values().sum::<u64>());
+    println!("Matches sequential histogram: {}", parallel == sequential);
+}